@@ -0,0 +1,72 @@
+use std::io::Cursor;
+
+use crate::reader::read_u16;
+use crate::DomainName;
+
+/// AFS cell database server subtype, see https://datatracker.ietf.org/doc/html/rfc1183#section-1
+pub const SUBTYPE_AFS_VOLUME_LOCATION: u16 = 1;
+
+/// DCE/NCA cell authenticated name server subtype
+pub const SUBTYPE_DCE_NAME_SERVER: u16 = 2;
+
+/// A server of the AFS or DCE cell named by the owner name.
+/// See https://datatracker.ietf.org/doc/html/rfc1183#section-1
+#[derive(Debug, Clone, PartialEq)]
+pub struct AfsdbRecord {
+    /// the kind of service the host provides
+    pub subtype: u16,
+    /// the host running the service
+    pub hostname: DomainName,
+}
+impl AfsdbRecord {
+    pub fn from_reader(reader: &mut Cursor<&[u8]>) -> Result<Self, std::io::Error> {
+        Ok(AfsdbRecord {
+            subtype: read_u16(reader)?,
+            hostname: DomainName::from_reader(reader)?,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.subtype.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&self.hostname.to_bytes());
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{message, record};
+    use crate::{DNSPacket, TypeField};
+
+    #[test]
+    fn decodes_afsdb_rdata() {
+        let rdata = AfsdbRecord {
+            subtype: SUBTYPE_AFS_VOLUME_LOCATION,
+            hostname: DomainName::from("afsdb.example.com"),
+        }
+        .to_bytes();
+        let data = message(
+            [0, 1, 0, 0],
+            &record("example.com", TypeField::AFSDB as u16, &rdata),
+        );
+        let packet = DNSPacket::from(&data).unwrap();
+        let afsdb = packet.answers[0].afsdb.as_ref().unwrap();
+        assert_eq!(afsdb.subtype, 1);
+        assert_eq!(afsdb.hostname.string, "afsdb.example.com");
+    }
+
+    #[test]
+    fn decodes_compressed_hostname() {
+        // the hostname is a pointer to the owner name, right after the header
+        let rdata = [0, 2, 0xc0, 12];
+        let data = message(
+            [0, 1, 0, 0],
+            &record("example.com", TypeField::AFSDB as u16, &rdata),
+        );
+        let packet = DNSPacket::from(&data).unwrap();
+        let afsdb = packet.answers[0].afsdb.as_ref().unwrap();
+        assert_eq!(afsdb.subtype, SUBTYPE_DCE_NAME_SERVER);
+        assert_eq!(afsdb.hostname.string, "example.com");
+    }
+}
@@ -0,0 +1,145 @@
+use std::fmt;
+use std::io::{Cursor, Error, ErrorKind};
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use crate::reader::{read_bytes, read_u16, read_u8};
+
+/// Address family numbers of IPv4 and IPv6, as assigned by IANA.
+pub const FAMILY_IPV4: u16 = 1;
+pub const FAMILY_IPV6: u16 = 2;
+
+const NEGATION: u8 = 0b1000_0000;
+const AFD_LENGTH_MASK: u8 = 0b0111_1111;
+
+/// A list of address prefixes.
+/// See https://datatracker.ietf.org/doc/html/rfc3123#section-4
+#[derive(Debug, Clone, PartialEq)]
+pub struct AplRecord(pub Vec<AplItem>);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AplItem {
+    /// the address family, see `FAMILY_IPV4` and `FAMILY_IPV6`
+    pub family: u16,
+    /// the length of the prefix in bits
+    pub prefix: u8,
+    /// whether the prefix is excluded from the list
+    pub negation: bool,
+    /// the address with its trailing zero bytes omitted
+    pub afdpart: Vec<u8>,
+}
+impl fmt::Display for AplItem {
+    /// Formats the item like a zone file does, e.g. `!1:10.0.0.0/8`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.negation {
+            write!(f, "!")?;
+        }
+        write!(f, "{}:", self.family)?;
+        match self.family {
+            FAMILY_IPV4 => write!(f, "{}", Ipv4Addr::from(padded::<4>(&self.afdpart)))?,
+            FAMILY_IPV6 => write!(f, "{}", Ipv6Addr::from(padded::<16>(&self.afdpart)))?,
+            _ => {
+                for byte in &self.afdpart {
+                    write!(f, "{:02x}", byte)?;
+                }
+            }
+        }
+        write!(f, "/{}", self.prefix)
+    }
+}
+
+/// Restores the trailing zeros omitted from the address.
+fn padded<const N: usize>(afdpart: &[u8]) -> [u8; N] {
+    let mut octets = [0u8; N];
+    let length = afdpart.len().min(N);
+    octets[..length].copy_from_slice(&afdpart[..length]);
+    octets
+}
+
+impl AplRecord {
+    /// Reads the rdata starting at the current position of the reader, `data_len`
+    /// bytes long.
+    pub fn from_reader(reader: &mut Cursor<&[u8]>, data_len: u16) -> Result<Self, std::io::Error> {
+        let end = reader.position() + u64::from(data_len);
+        let mut items = vec![];
+        while reader.position() < end {
+            let family = read_u16(reader)?;
+            let prefix = read_u8(reader)?;
+            let length = read_u8(reader)?;
+            let afdpart = read_bytes(reader, (length & AFD_LENGTH_MASK) as usize)?;
+            items.push(AplItem {
+                family,
+                prefix,
+                negation: length & NEGATION != 0,
+                afdpart,
+            });
+        }
+        if reader.position() > end {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "APL item overflows rdata",
+            ));
+        }
+        Ok(AplRecord(items))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        for item in &self.0 {
+            bytes.extend_from_slice(&item.family.to_be_bytes());
+            bytes.push(item.prefix);
+            let length = item.afdpart.len() as u8 & AFD_LENGTH_MASK;
+            bytes.push(if item.negation {
+                length | NEGATION
+            } else {
+                length
+            });
+            bytes.extend_from_slice(&item.afdpart);
+        }
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{message, record};
+    use crate::{DNSPacket, TypeField};
+
+    #[test]
+    fn decodes_two_ipv4_prefixes() {
+        // 1:192.168.0.0/16 !1:10.0.0.0/8
+        let rdata = [0, 1, 16, 2, 192, 168, 0, 1, 8, 0x81, 10];
+        let data = message(
+            [0, 1, 0, 0],
+            &record("example.com", TypeField::APL as u16, &rdata),
+        );
+        let packet = DNSPacket::from(&data).unwrap();
+        let apl = packet.answers[0].apl.as_ref().unwrap();
+        assert_eq!(
+            apl.0,
+            vec![
+                AplItem {
+                    family: FAMILY_IPV4,
+                    prefix: 16,
+                    negation: false,
+                    afdpart: vec![192, 168],
+                },
+                AplItem {
+                    family: FAMILY_IPV4,
+                    prefix: 8,
+                    negation: true,
+                    afdpart: vec![10],
+                },
+            ]
+        );
+        assert_eq!(apl.0[1].to_string(), "!1:10.0.0.0/8");
+        assert_eq!(apl.to_bytes(), rdata);
+    }
+
+    #[test]
+    fn rejects_item_overflowing_rdata() {
+        let rdata = [0, 1, 16, 4, 192, 168];
+        let mut reader = Cursor::new(&rdata[..]);
+        assert!(AplRecord::from_reader(&mut reader, rdata.len() as u16).is_err());
+    }
+}
@@ -0,0 +1,125 @@
+use std::net::Ipv4Addr;
+
+use implement_dns::{
+    resolve_with_server_on_port, DomainName, Resolver, ResolverConfig, TypeField, DEFAULT_DNS_PORT,
+};
+
+const USAGE: &str = "Usage: dns-lookup <name> [--type A] [--server 1.1.1.1] [--port 53]";
+
+#[derive(Debug)]
+struct Args {
+    name: DomainName,
+    type_field: TypeField,
+    /// resolve recursively through this server instead of iteratively from the root
+    server: Option<Ipv4Addr>,
+    port: u16,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut name = None;
+    let mut type_field = TypeField::A;
+    let mut server = None;
+    let mut port = DEFAULT_DNS_PORT;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--type" | "-t" => {
+                let value = args.next().ok_or("--type requires a value")?;
+                type_field = value
+                    .parse()
+                    .map_err(|_| format!("Unknown type {}", value))?;
+            }
+            "--server" | "-s" => {
+                let value = args.next().ok_or("--server requires a value")?;
+                server = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("Invalid server address {}", value))?,
+                );
+            }
+            "--port" | "-p" => {
+                let value = args.next().ok_or("--port requires a value")?;
+                port = value
+                    .parse()
+                    .map_err(|_| format!("Invalid port {}", value))?;
+            }
+            _ if arg.starts_with('-') => return Err(format!("Unknown option {}", arg)),
+            _ if name.is_none() => {
+                name = Some(DomainName::parse(&arg).map_err(|e| format!("{}: {}", arg, e))?)
+            }
+            _ => return Err(format!("Unexpected argument {}", arg)),
+        }
+    }
+
+    Ok(Args {
+        name: name.ok_or("Missing name to look up")?,
+        type_field,
+        server,
+        port,
+    })
+}
+
+fn main() -> Result<(), std::io::Error> {
+    env_logger::init();
+
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    match args.server {
+        Some(server) => {
+            let packet =
+                resolve_with_server_on_port(&args.name, args.type_field, server, args.port)?;
+            print!("{}", packet);
+        }
+        None => {
+            println!(";; ANSWER SECTION:");
+            let resolver = Resolver::new(ResolverConfig {
+                port: args.port,
+                ..Default::default()
+            });
+            for record in resolver.resolve_records(&args.name, args.type_field)? {
+                println!("{}", record);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Result<Args, String> {
+        parse_args(args.iter().map(|x| x.to_string()))
+    }
+
+    #[test]
+    fn parses_type() {
+        let args = args(&["example.com", "--type", "MX"]).unwrap();
+        assert_eq!(args.name.string, "example.com");
+        assert_eq!(args.type_field, TypeField::MX);
+        assert_eq!(args.server, None);
+        assert_eq!(args.port, DEFAULT_DNS_PORT);
+    }
+
+    #[test]
+    fn parses_server_and_port() {
+        let args = args(&["-s", "1.1.1.1", "example.com", "-p", "5353"]).unwrap();
+        assert_eq!(args.type_field, TypeField::A);
+        assert_eq!(args.server, Some(Ipv4Addr::new(1, 1, 1, 1)));
+        assert_eq!(args.port, 5353);
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        assert!(args(&["example.com", "--type", "NOPE"]).is_err());
+        assert!(args(&["example.com", "--type"]).is_err());
+        assert!(args(&["--type", "MX"]).is_err());
+        assert!(args(&["example.com", "other.com"]).is_err());
+    }
+}
@@ -0,0 +1,326 @@
+use std::collections::HashMap;
+use std::io::Error;
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::error::clone_io_error;
+use crate::{resolve_records, Clock, DNSRecord, DomainName, SystemClock, TypeField};
+
+/// Names are case-insensitive, so they are stored lowercased.
+type CacheKey = (String, TypeField);
+
+#[derive(Debug)]
+struct CacheEntry {
+    records: Vec<DNSRecord>,
+    cached_at: Instant,
+    expires_at: Instant,
+}
+
+type SharedResult = Result<Vec<DNSRecord>, std::io::Error>;
+
+/// A lookup that is currently being resolved by another thread.
+#[derive(Debug, Default)]
+struct InFlight {
+    result: Mutex<Option<SharedResult>>,
+    done: Condvar,
+}
+impl InFlight {
+    fn wait(&self) -> SharedResult {
+        let mut result = self.result.lock().unwrap();
+        while result.is_none() {
+            result = self.done.wait(result).unwrap();
+        }
+        match result.as_ref().unwrap() {
+            Ok(records) => Ok(records.clone()),
+            Err(error) => Err(clone_io_error(error)),
+        }
+    }
+
+    fn finish(&self, result: &SharedResult) {
+        let shared = match result {
+            Ok(records) => Ok(records.clone()),
+            Err(error) => Err(clone_io_error(error)),
+        };
+        *self.result.lock().unwrap_or_else(PoisonError::into_inner) = Some(shared);
+        self.done.notify_all();
+    }
+}
+
+/// Owns an in-flight lookup until its result is shared. If the lookup panics, it is
+/// removed all the same and its waiters get an error instead of waiting forever.
+struct InFlightGuard<'a> {
+    cache: &'a Cache,
+    key: CacheKey,
+    in_flight: Arc<InFlight>,
+    finished: bool,
+}
+impl InFlightGuard<'_> {
+    fn finish(mut self, result: &SharedResult) {
+        self.cache.remove_in_flight(&self.key);
+        self.in_flight.finish(result);
+        self.finished = true;
+    }
+}
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.cache.remove_in_flight(&self.key);
+            self.in_flight
+                .finish(&Err(Error::other("Lookup of the same name panicked")));
+        }
+    }
+}
+
+/// A cache of resolved records that can be shared between threads.
+///
+/// Records are kept for the smallest TTL of their RRset. Concurrent lookups of the
+/// same name and type are coalesced, so only one of them reaches the network and
+/// the others wait for its result.
+#[derive(Debug)]
+pub struct Cache {
+    clock: Arc<dyn Clock>,
+    entries: Mutex<HashMap<CacheKey, CacheEntry>>,
+    in_flight: Mutex<HashMap<CacheKey, Arc<InFlight>>>,
+}
+impl Default for Cache {
+    fn default() -> Self {
+        Cache::new()
+    }
+}
+impl Cache {
+    pub fn new() -> Self {
+        Cache::with_clock(Arc::new(SystemClock))
+    }
+
+    /// A cache whose entries expire according to `clock`.
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Cache {
+            clock,
+            entries: Mutex::new(HashMap::new()),
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn key(domain_name: &DomainName, type_field: TypeField) -> CacheKey {
+        (domain_name.string.to_ascii_lowercase(), type_field)
+    }
+
+    /// Returns the cached records, unless they have expired, with their TTL reduced
+    /// by how long they have been cached.
+    pub fn get(&self, domain_name: &DomainName, type_field: TypeField) -> Option<Vec<DNSRecord>> {
+        let key = Cache::key(domain_name, type_field);
+        let now = self.clock.now();
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(&key) {
+            Some(entry) if entry.expires_at > now => Some(
+                entry
+                    .records
+                    .iter()
+                    .map(|x| DNSRecord {
+                        ttl: x.ttl_remaining(entry.cached_at, now),
+                        ..x.clone()
+                    })
+                    .collect(),
+            ),
+            Some(_) => {
+                entries.remove(&key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&self, domain_name: &DomainName, type_field: TypeField, records: Vec<DNSRecord>) {
+        let Some(ttl) = records.iter().map(|x| x.ttl).min() else {
+            return;
+        };
+        if ttl == 0 {
+            return;
+        }
+        let now = self.clock.now();
+        let entry = CacheEntry {
+            records,
+            cached_at: now,
+            expires_at: now + Duration::from_secs(u64::from(ttl)),
+        };
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(Cache::key(domain_name, type_field), entry);
+    }
+
+    /// Like `resolve_records`, but answers from the cache when possible.
+    pub fn resolve(
+        &self,
+        domain_name: &DomainName,
+        type_field: TypeField,
+    ) -> Result<Vec<DNSRecord>, std::io::Error> {
+        self.resolve_with(domain_name, type_field, resolve_records)
+    }
+
+    /// Answers from the cache when possible, otherwise calls `lookup` unless another
+    /// thread is already looking up the same name and type, in which case its result
+    /// is shared.
+    pub fn resolve_with<F>(
+        &self,
+        domain_name: &DomainName,
+        type_field: TypeField,
+        lookup: F,
+    ) -> Result<Vec<DNSRecord>, std::io::Error>
+    where
+        F: FnOnce(&DomainName, TypeField) -> Result<Vec<DNSRecord>, std::io::Error>,
+    {
+        if let Some(records) = self.get(domain_name, type_field) {
+            return Ok(records);
+        }
+
+        let key = Cache::key(domain_name, type_field);
+        let in_flight = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            if let Some(pending) = in_flight.get(&key) {
+                let pending = pending.clone();
+                drop(in_flight);
+                return pending.wait();
+            }
+            // the previous lookup may have completed since we checked
+            if let Some(records) = self.get(domain_name, type_field) {
+                return Ok(records);
+            }
+            let pending = Arc::new(InFlight::default());
+            in_flight.insert(key.clone(), pending.clone());
+            InFlightGuard {
+                cache: self,
+                key,
+                in_flight: pending,
+                finished: false,
+            }
+        };
+
+        let result = lookup(domain_name, type_field);
+        if let Ok(records) = &result {
+            self.insert(domain_name, type_field, records.clone());
+        }
+        in_flight.finish(&result);
+        result
+    }
+
+    fn remove_in_flight(&self, key: &CacheKey) {
+        self.in_flight
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Barrier;
+    use std::thread;
+
+    use super::*;
+    use crate::{ClassField, DnsError, MockClock};
+
+    const THREADS: usize = 8;
+
+    fn a_record(name: &str) -> DNSRecord {
+        DNSRecord::new(
+            DomainName::from(name),
+            TypeField::A,
+            ClassField::IN,
+            300,
+            &[1, 2, 3, 4],
+        )
+        .unwrap()
+    }
+
+    /// Looks up example.com A on `THREADS` threads at once with `lookup`.
+    fn resolve_concurrently<F>(cache: &Cache, lookup: F) -> Vec<thread::Result<SharedResult>>
+    where
+        F: Fn(&DomainName, TypeField) -> SharedResult + Sync,
+    {
+        let barrier = Barrier::new(THREADS);
+        thread::scope(|scope| {
+            let handles: Vec<_> = (0..THREADS)
+                .map(|_| {
+                    scope.spawn(|| {
+                        barrier.wait();
+                        cache.resolve_with(&DomainName::from("example.com"), TypeField::A, &lookup)
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|x| x.join()).collect()
+        })
+    }
+
+    #[test]
+    fn concurrent_lookups_send_one_query() {
+        let cache = Cache::new();
+        let queries = AtomicUsize::new(0);
+        let results = resolve_concurrently(&cache, |name, _| {
+            queries.fetch_add(1, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(200));
+            Ok(vec![a_record(&name.string)])
+        });
+
+        assert_eq!(queries.load(Ordering::SeqCst), 1);
+        for result in results {
+            assert_eq!(result.unwrap().unwrap(), vec![a_record("example.com")]);
+        }
+    }
+
+    #[test]
+    fn waiters_share_the_error() {
+        let cache = Cache::new();
+        let queries = AtomicUsize::new(0);
+        let results = resolve_concurrently(&cache, |name, _| {
+            queries.fetch_add(1, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(200));
+            Err(DnsError::NXDomain {
+                name: name.string.clone(),
+            }
+            .into())
+        });
+
+        assert_eq!(queries.load(Ordering::SeqCst), 1);
+        for result in results {
+            let error = result.unwrap().unwrap_err();
+            assert_eq!(
+                DnsError::from_io(&error),
+                Some(&DnsError::NXDomain {
+                    name: "example.com".to_string()
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn waiters_are_released_when_the_lookup_panics() {
+        let cache = Cache::new();
+        let results = resolve_concurrently(&cache, |_, _| {
+            thread::sleep(Duration::from_millis(200));
+            panic!("lookup failed");
+        });
+
+        assert_eq!(results.iter().filter(|x| x.is_err()).count(), 1);
+        for result in results.into_iter().flatten() {
+            assert!(result.is_err());
+        }
+        assert!(cache.in_flight.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn entries_expire_after_their_ttl() {
+        let clock = Arc::new(MockClock::new());
+        let cache = Cache::with_clock(clock.clone());
+        let name = DomainName::from("example.com");
+        cache.insert(&name, TypeField::A, vec![a_record("example.com")]);
+
+        clock.advance(Duration::from_secs(100));
+        let records = cache.get(&name, TypeField::A).unwrap();
+        assert_eq!(records[0].ttl, 200);
+
+        clock.advance(Duration::from_secs(200));
+        assert!(cache.get(&name, TypeField::A).is_none());
+    }
+}
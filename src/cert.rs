@@ -0,0 +1,76 @@
+use std::io::{Cursor, Error, ErrorKind};
+
+use crate::reader::{read_bytes, read_u16, read_u8};
+
+/// A certificate or certificate revocation list, e.g. X.509 or OpenPGP.
+/// See https://datatracker.ietf.org/doc/html/rfc4398#section-2
+#[derive(Debug, Clone, PartialEq)]
+pub struct CertRecord {
+    /// the format of the certificate, e.g. 1 for X.509
+    pub cert_type: u16,
+    /// identifies the key the certificate is for, 0 if the algorithm is 0
+    pub key_tag: u16,
+    /// the algorithm of the key, as in DNSSEC
+    pub algorithm: u8,
+    pub certificate: Vec<u8>,
+}
+impl CertRecord {
+    /// Reads the rdata starting at the current position of the reader, `data_len`
+    /// bytes long.
+    pub fn from_reader(reader: &mut Cursor<&[u8]>, data_len: u16) -> Result<Self, std::io::Error> {
+        let certificate_len = data_len
+            .checked_sub(5)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "CERT rdata too short"))?;
+        Ok(CertRecord {
+            cert_type: read_u16(reader)?,
+            key_tag: read_u16(reader)?,
+            algorithm: read_u8(reader)?,
+            certificate: read_bytes(reader, certificate_len as usize)?,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.cert_type.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&self.key_tag.to_be_bytes());
+        bytes.push(self.algorithm);
+        bytes.extend_from_slice(&self.certificate);
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{message, record};
+    use crate::{DNSPacket, TypeField};
+
+    #[test]
+    fn decodes_cert_rdata() {
+        // a PKIX certificate, key tag 12345, RSA/SHA-256
+        let mut rdata = vec![0, 1, 0x30, 0x39, 8];
+        rdata.extend_from_slice(&[0x30, 0x82, 0x01, 0x0a]);
+        let data = message(
+            [0, 1, 0, 0],
+            &record("example.com", TypeField::CERT as u16, &rdata),
+        );
+        let packet = DNSPacket::from(&data).unwrap();
+        let cert = packet.answers[0].cert.as_ref().unwrap();
+        assert_eq!(
+            *cert,
+            CertRecord {
+                cert_type: 1,
+                key_tag: 12345,
+                algorithm: 8,
+                certificate: vec![0x30, 0x82, 0x01, 0x0a],
+            }
+        );
+        assert_eq!(cert.to_bytes(), rdata);
+    }
+
+    #[test]
+    fn rejects_short_rdata() {
+        let rdata = [0, 1, 0x30, 0x39];
+        let mut reader = Cursor::new(&rdata[..]);
+        assert!(CertRecord::from_reader(&mut reader, rdata.len() as u16).is_err());
+    }
+}
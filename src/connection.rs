@@ -0,0 +1,178 @@
+use std::io::{Error, ErrorKind};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::time::Duration;
+
+use crate::UDP_PAYLOAD_SIZE;
+use crate::{
+    build_query_with_options, check_udp_size, DNSPacket, DomainName, QueryOptions, TypeField,
+};
+
+/// A UDP socket talking to a single server, that can be reused for many queries
+/// instead of binding a new socket for each of them. The socket isn't connected, so
+/// that responses from other addresses reach it and can be rejected.
+#[derive(Debug)]
+pub struct UdpConnection {
+    socket: UdpSocket,
+    server: SocketAddr,
+}
+impl UdpConnection {
+    pub fn connect(server: IpAddr, port: u16, timeout: Duration) -> Result<Self, std::io::Error> {
+        let source = match server {
+            IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        };
+        UdpConnection::connect_from(source, server, port, timeout)
+    }
+
+    /// Like `connect`, but sends from the local address `source`, e.g. to pick the
+    /// interface of a multi-homed host. It must be of the same family as `server`.
+    pub fn connect_from(
+        source: IpAddr,
+        server: IpAddr,
+        port: u16,
+        timeout: Duration,
+    ) -> Result<Self, std::io::Error> {
+        let socket = UdpSocket::bind((source, 0))?;
+        socket.set_read_timeout(Some(timeout))?;
+        Ok(UdpConnection {
+            socket,
+            server: SocketAddr::new(server, port),
+        })
+    }
+
+    pub fn server(&self) -> IpAddr {
+        self.server.ip()
+    }
+
+    /// The local address queries are sent from.
+    pub fn local_addr(&self) -> Result<SocketAddr, std::io::Error> {
+        self.socket.local_addr()
+    }
+
+    /// Sends `query` and waits for the response with the same id. Late responses to
+    /// earlier queries on this socket are skipped. Fails without sending queries too
+    /// large for UDP, see `check_udp_size`.
+    pub fn query(&self, query: &[u8]) -> Result<DNSPacket, std::io::Error> {
+        let id = query
+            .get(..2)
+            .map(|x| u16::from_be_bytes([x[0], x[1]]))
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "Query is missing its header"))?;
+        check_udp_size(query)?;
+        self.socket.send_to(query, self.server)?;
+
+        let mut buf = [0; UDP_PAYLOAD_SIZE as usize];
+        loop {
+            let (amt, src) = self.socket.recv_from(&mut buf)?;
+            log::debug!("Received {} bytes from {}", amt, src);
+            // a reply from anyone but the server we asked is likely spoofed
+            if src != self.server {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Response from unexpected address {}", src),
+                ));
+            }
+            let packet = DNSPacket::from(&buf[..amt])?;
+            if packet.header.id == id {
+                return Ok(packet);
+            }
+            log::debug!(
+                "Skipping response {} while waiting for {}",
+                packet.header.id,
+                id
+            );
+        }
+    }
+
+    /// Asks the server to resolve the name recursively, see `resolve_with_server`.
+    pub fn resolve(
+        &self,
+        domain_name: &DomainName,
+        type_field: TypeField,
+    ) -> Result<DNSPacket, std::io::Error> {
+        let options = QueryOptions {
+            recursion_desired: true,
+            ..Default::default()
+        };
+        self.query(&build_query_with_options(domain_name, type_field, &options))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{a, answer, start_servers};
+    use crate::{build_query, DNSPacket};
+
+    const TIMEOUT: Duration = Duration::from_millis(200);
+
+    /// Receives a query on `socket` and answers it from `reply_from`.
+    fn answer_from(socket: &UdpSocket, reply_from: &UdpSocket) {
+        let mut buf = [0; 512];
+        let (amt, client) = socket.recv_from(&mut buf).unwrap();
+        let query = DNSPacket::from(&buf[..amt]).unwrap();
+        let response =
+            DNSPacket::response_to(&query, vec![a("example.com", [192, 0, 2, 1].into())]);
+        reply_from.send_to(&response.to_bytes(), client).unwrap();
+    }
+
+    #[test]
+    fn rejects_response_from_unexpected_address() {
+        let server = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let spoofer = UdpSocket::bind((Ipv4Addr::new(127, 0, 0, 2), 0)).unwrap();
+        let port = server.local_addr().unwrap().port();
+        let connection = UdpConnection::connect(Ipv4Addr::LOCALHOST.into(), port, TIMEOUT).unwrap();
+
+        let name = DomainName::from("example.com");
+        std::thread::scope(|scope| {
+            scope.spawn(|| answer_from(&server, &spoofer));
+            let error = connection.resolve(&name, TypeField::A).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidData);
+            assert!(error.to_string().contains("unexpected address"));
+            scope.spawn(|| answer_from(&server, &server));
+            assert!(connection.resolve(&name, TypeField::A).is_ok());
+        });
+    }
+
+    #[test]
+    fn reuses_socket_for_two_queries() {
+        let (port, servers) = start_servers(vec![(
+            Ipv4Addr::LOCALHOST.into(),
+            Box::new(|query| {
+                let name = query.questions[0].name.string.clone();
+                Some(answer(query, vec![a(&name, [192, 0, 2, 1].into())]))
+            }),
+        )]);
+        let connection = UdpConnection::connect(Ipv4Addr::LOCALHOST.into(), port, TIMEOUT).unwrap();
+        let local_addr = connection.local_addr().unwrap();
+
+        for name in ["a.example.com", "b.example.com"] {
+            let response = connection
+                .resolve(&DomainName::from(name), TypeField::A)
+                .unwrap();
+            assert_eq!(response.answers[0].name.string, name);
+        }
+        assert_eq!(connection.local_addr().unwrap(), local_addr);
+        assert_eq!(servers[0].queries().len(), 2);
+    }
+
+    #[test]
+    fn binds_to_source_address() {
+        let source = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 5));
+        let server = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let port = server.local_addr().unwrap().port();
+        let connection =
+            UdpConnection::connect_from(source, Ipv4Addr::LOCALHOST.into(), port, TIMEOUT).unwrap();
+        assert_eq!(connection.local_addr().unwrap().ip(), source);
+
+        connection
+            .socket
+            .send_to(
+                &build_query(&DomainName::from("example.com"), TypeField::A),
+                connection.server,
+            )
+            .unwrap();
+        let mut buf = [0; 512];
+        let (_, client) = server.recv_from(&mut buf).unwrap();
+        assert_eq!(client.ip(), source);
+    }
+}
@@ -0,0 +1,144 @@
+use std::fmt::Write;
+use std::io::Cursor;
+
+use crate::{DNSHeader, DNSQuestion, DNSRecord};
+
+const BYTES_PER_LINE: usize = 12;
+
+/// Writes `data[start..end]` as hex, next to its offset, with the annotation on the
+/// first line.
+fn write_bytes(out: &mut String, data: &[u8], start: usize, end: usize, annotation: &str) {
+    let end = end.min(data.len());
+    let mut offset = start;
+    let mut annotation = Some(annotation);
+    loop {
+        let line_end = (offset + BYTES_PER_LINE).min(end);
+        let hex: Vec<String> = data[offset..line_end]
+            .iter()
+            .map(|x| format!("{:02x}", x))
+            .collect();
+        let hex = hex.join(" ");
+        match annotation.take() {
+            Some(annotation) => writeln!(
+                out,
+                "{:04x}  {:width$}  {}",
+                offset,
+                hex,
+                annotation,
+                width = BYTES_PER_LINE * 3 - 1
+            ),
+            None => writeln!(out, "{:04x}  {}", offset, hex),
+        }
+        .unwrap();
+        offset = line_end;
+        if offset >= end {
+            break;
+        }
+    }
+}
+
+fn write_error(out: &mut String, data: &[u8], start: usize, error: std::io::Error) {
+    write_bytes(out, data, start, data.len(), &format!("error: {}", error));
+}
+
+pub fn debug_dump(data: &[u8]) -> String {
+    let mut out = String::new();
+    let mut reader = Cursor::new(data);
+
+    let header = match DNSHeader::from_reader(&mut reader) {
+        Ok(header) => header,
+        Err(error) => {
+            write_error(&mut out, data, 0, error);
+            return out;
+        }
+    };
+    writeln!(out, ";; HEADER").unwrap();
+    let fields = [
+        format!("id: {}", header.id),
+        format!(
+            "flags: {:#06x} (opcode: {}, rcode: {})",
+            header.flags.bits(),
+            header.flags.opcode(),
+            header.flags.rcode()
+        ),
+        format!("questions: {}", header.num_questions),
+        format!("answers: {}", header.num_answers),
+        format!("authorities: {}", header.num_authorities),
+        format!("additionals: {}", header.num_additionals),
+    ];
+    for (i, field) in fields.iter().enumerate() {
+        write_bytes(&mut out, data, i * 2, i * 2 + 2, field);
+    }
+
+    writeln!(out, ";; QUESTION SECTION").unwrap();
+    for _ in 0..header.num_questions {
+        let start = reader.position() as usize;
+        match DNSQuestion::from_reader(&mut reader) {
+            Ok(question) => write_bytes(
+                &mut out,
+                data,
+                start,
+                reader.position() as usize,
+                &question.to_string(),
+            ),
+            Err(error) => {
+                write_error(&mut out, data, start, error);
+                return out;
+            }
+        }
+    }
+
+    let sections = [
+        ("ANSWER", header.num_answers),
+        ("AUTHORITY", header.num_authorities),
+        ("ADDITIONAL", header.num_additionals),
+    ];
+    for (title, count) in sections {
+        writeln!(out, ";; {} SECTION", title).unwrap();
+        for _ in 0..count {
+            let start = reader.position() as usize;
+            match DNSRecord::from_reader(&mut reader) {
+                Ok(record) => write_bytes(
+                    &mut out,
+                    data,
+                    start,
+                    reader.position() as usize,
+                    &record.to_string(),
+                ),
+                Err(error) => {
+                    write_error(&mut out, data, start, error);
+                    return out;
+                }
+            }
+        }
+    }
+
+    let end = reader.position() as usize;
+    if end < data.len() {
+        write_bytes(&mut out, data, end, data.len(), "trailing bytes");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{message, record};
+
+    #[test]
+    fn shows_hex_next_to_decoded_fields() {
+        // the id of the test messages is 0x1234
+        let data = message([0, 1, 0, 0], &record("example.com", 1, &[1, 2, 3, 4]));
+        let dump = debug_dump(&data);
+        let id_line = dump.lines().find(|x| x.starts_with("0000")).unwrap();
+        assert!(id_line.contains("12 34"));
+        assert!(id_line.contains(&format!("id: {}", 0x1234)));
+        assert!(dump.contains("example.com"));
+    }
+
+    #[test]
+    fn shows_where_parsing_failed() {
+        let data = message([0, 1, 0, 0], &[]);
+        assert!(debug_dump(&data).contains("error: "));
+    }
+}
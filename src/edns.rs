@@ -0,0 +1,482 @@
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+use std::net::IpAddr;
+use std::sync::Mutex;
+
+/// Option code of the name server identifier, empty in queries asking for it.
+/// See https://datatracker.ietf.org/doc/html/rfc5001#section-2.3
+pub const NSID_OPTION_CODE: u16 = 3;
+
+/// Option code of the client subnet, the network a query originates from.
+/// See https://datatracker.ietf.org/doc/html/rfc7871#section-6
+pub const CLIENT_SUBNET_OPTION_CODE: u16 = 8;
+
+/// Option code of DNS cookies.
+/// See https://datatracker.ietf.org/doc/html/rfc7873#section-4
+pub const COOKIE_OPTION_CODE: u16 = 10;
+
+/// The client cookie is always 8 bytes long, the server cookie that follows it in
+/// responses is between 8 and 32 bytes long.
+pub const CLIENT_COOKIE_LEN: usize = 8;
+
+/// Option code of the padding option, its data is made of zeros.
+/// See https://datatracker.ietf.org/doc/html/rfc7830#section-3
+pub const PADDING_OPTION_CODE: u16 = 12;
+const SERVER_COOKIE_LEN: std::ops::RangeInclusive<usize> = 8..=32;
+
+/// The only EDNS version defined so far, and the one we speak.
+pub const EDNS_VERSION: u8 = 0;
+
+/// Extended RCODE of a server that doesn't implement the EDNS version of the query.
+/// See https://datatracker.ietf.org/doc/html/rfc6891#section-9
+pub const BADVERS: u16 = 16;
+
+/// The DNSSEC OK bit of the flags, asks for DNSSEC records in the response.
+/// See https://datatracker.ietf.org/doc/html/rfc3225#section-3
+const DO: u16 = 0b1000_0000_0000_0000;
+
+/// An EDNS option decoded according to its code. Options that are unknown, or too
+/// short for their code, are kept as they are.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EdnsOption {
+    Cookie {
+        client: [u8; CLIENT_COOKIE_LEN],
+        /// empty in queries
+        server: Vec<u8>,
+    },
+    ClientSubnet {
+        /// 1 for IPv4, 2 for IPv6
+        family: u16,
+        source_prefix_len: u8,
+        scope_prefix_len: u8,
+        /// the significant bytes of the address, as many as the prefix covers
+        address: Vec<u8>,
+    },
+    Nsid(Vec<u8>),
+    /// the number of padding bytes
+    Padding(usize),
+    Unknown(u16, Vec<u8>),
+}
+impl EdnsOption {
+    pub fn from_parts(code: u16, data: &[u8]) -> Self {
+        match code {
+            COOKIE_OPTION_CODE if data.len() >= CLIENT_COOKIE_LEN => EdnsOption::Cookie {
+                client: data[..CLIENT_COOKIE_LEN].try_into().unwrap(),
+                server: data[CLIENT_COOKIE_LEN..].to_vec(),
+            },
+            CLIENT_SUBNET_OPTION_CODE if data.len() >= 4 => EdnsOption::ClientSubnet {
+                family: u16::from_be_bytes([data[0], data[1]]),
+                source_prefix_len: data[2],
+                scope_prefix_len: data[3],
+                address: data[4..].to_vec(),
+            },
+            NSID_OPTION_CODE => EdnsOption::Nsid(data.to_vec()),
+            PADDING_OPTION_CODE => EdnsOption::Padding(data.len()),
+            _ => EdnsOption::Unknown(code, data.to_vec()),
+        }
+    }
+
+    pub fn code(&self) -> u16 {
+        match self {
+            EdnsOption::Cookie { .. } => COOKIE_OPTION_CODE,
+            EdnsOption::ClientSubnet { .. } => CLIENT_SUBNET_OPTION_CODE,
+            EdnsOption::Nsid(_) => NSID_OPTION_CODE,
+            EdnsOption::Padding(_) => PADDING_OPTION_CODE,
+            EdnsOption::Unknown(code, _) => *code,
+        }
+    }
+
+    /// The data of the option as sent on the wire, after its code and length.
+    pub fn data(&self) -> Vec<u8> {
+        match self {
+            EdnsOption::Cookie { client, server } => [&client[..], server].concat(),
+            EdnsOption::ClientSubnet {
+                family,
+                source_prefix_len,
+                scope_prefix_len,
+                address,
+            } => {
+                let mut bytes = family.to_be_bytes().to_vec();
+                bytes.extend_from_slice(&[*source_prefix_len, *scope_prefix_len]);
+                bytes.extend_from_slice(address);
+                bytes
+            }
+            EdnsOption::Nsid(data) => data.clone(),
+            EdnsOption::Padding(length) => vec![0; *length],
+            EdnsOption::Unknown(_, data) => data.clone(),
+        }
+    }
+}
+
+/// The OPT pseudo-record carries EDNS(0) information in the additional section.
+/// Its CLASS and TTL fields are repurposed, so they are decoded here instead.
+/// See https://datatracker.ietf.org/doc/html/rfc6891#section-6.1.2
+#[derive(Debug, Clone, PartialEq)]
+pub struct OptRecord {
+    /// largest UDP payload the sender can reassemble, stored in the CLASS field
+    pub udp_payload_size: u16,
+    /// upper 8 bits of the 12-bit extended RCODE
+    pub extended_rcode: u8,
+    /// EDNS version, only 0 is defined
+    pub version: u8,
+    /// the remaining 16 bits of the TTL field
+    pub flags: u16,
+    /// options in wire order
+    pub options: Vec<EdnsOption>,
+}
+impl OptRecord {
+    pub fn new(udp_payload_size: u16) -> Self {
+        OptRecord {
+            udp_payload_size,
+            extended_rcode: 0,
+            version: EDNS_VERSION,
+            flags: 0,
+            options: vec![],
+        }
+    }
+
+    /// Decode the OPT record from the CLASS and TTL fields and the rdata of a
+    /// resource record.
+    pub fn from_parts(class: u16, ttl: u32, data: &[u8]) -> Result<Self, std::io::Error> {
+        let [extended_rcode, version, flags_high, flags_low] = ttl.to_be_bytes();
+
+        let mut options = vec![];
+        let mut rest = data;
+        while !rest.is_empty() {
+            if rest.len() < 4 {
+                return Err(Error::new(ErrorKind::InvalidData, "Truncated EDNS option"));
+            }
+            let code = u16::from_be_bytes([rest[0], rest[1]]);
+            let length = u16::from_be_bytes([rest[2], rest[3]]) as usize;
+            let value = rest[4..]
+                .get(..length)
+                .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Truncated EDNS option"))?;
+            options.push(EdnsOption::from_parts(code, value));
+            rest = &rest[4 + length..];
+        }
+
+        Ok(OptRecord {
+            udp_payload_size: class,
+            extended_rcode,
+            version,
+            flags: u16::from_be_bytes([flags_high, flags_low]),
+            options,
+        })
+    }
+
+    pub fn ttl(&self) -> u32 {
+        let [flags_high, flags_low] = self.flags.to_be_bytes();
+        u32::from_be_bytes([self.extended_rcode, self.version, flags_high, flags_low])
+    }
+
+    pub fn rdata(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for option in &self.options {
+            let value = option.data();
+            bytes.extend_from_slice(&option.code().to_be_bytes());
+            bytes.extend_from_slice(&(value.len() as u16).to_be_bytes());
+            bytes.extend_from_slice(&value);
+        }
+        bytes
+    }
+
+    /// Encode the whole resource record, the owner of an OPT record is always the root.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let rdata = self.rdata();
+        let mut bytes = vec![0];
+        bytes.extend_from_slice(&(crate::TypeField::OPT as u16).to_be_bytes());
+        bytes.extend_from_slice(&self.udp_payload_size.to_be_bytes());
+        bytes.extend_from_slice(&self.ttl().to_be_bytes());
+        bytes.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(&rdata);
+        bytes
+    }
+
+    /// Returns the data of the first option with the given code.
+    pub fn option(&self, code: u16) -> Option<Vec<u8>> {
+        self.options
+            .iter()
+            .find(|x| x.code() == code)
+            .map(EdnsOption::data)
+    }
+
+    pub fn dnssec_ok(&self) -> bool {
+        self.flags & DO != 0
+    }
+
+    pub fn with_dnssec_ok(mut self, value: bool) -> Self {
+        if value {
+            self.flags |= DO;
+        } else {
+            self.flags &= !DO;
+        }
+        self
+    }
+
+    pub fn with_cookie(self, client_cookie: [u8; CLIENT_COOKIE_LEN]) -> Self {
+        self.with_cookies(client_cookie, &[])
+    }
+
+    /// Sends the server cookie the server returned last after our client cookie,
+    /// so it can tell we are the same client.
+    /// See https://datatracker.ietf.org/doc/html/rfc7873#section-5.2
+    pub fn with_cookies(
+        mut self,
+        client_cookie: [u8; CLIENT_COOKIE_LEN],
+        server_cookie: &[u8],
+    ) -> Self {
+        self.options.push(EdnsOption::Cookie {
+            client: client_cookie,
+            server: server_cookie.to_vec(),
+        });
+        self
+    }
+
+    pub fn with_padding(mut self, length: usize) -> Self {
+        self.options.push(EdnsOption::Padding(length));
+        self
+    }
+
+    /// Asks the server to tell which instance answered, e.g. behind anycast.
+    pub fn with_nsid_request(mut self) -> Self {
+        self.options.push(EdnsOption::Nsid(vec![]));
+        self
+    }
+
+    /// The identifier of the server that answered, usually printable ASCII but its
+    /// content is up to the operator.
+    pub fn nsid(&self) -> Option<Vec<u8>> {
+        self.option(NSID_OPTION_CODE)
+    }
+
+    /// The client cookie followed, in responses, by the server cookie.
+    pub fn cookie(&self) -> Option<Vec<u8>> {
+        self.option(COOKIE_OPTION_CODE)
+    }
+
+    /// Checks that a response echoes back our client cookie next to a server cookie
+    /// of valid length, anything else may have been spoofed by an off-path attacker.
+    /// Servers that don't implement cookies answer without one, which is only an
+    /// error once the server has been seen to return cookies, i.e. when `required`.
+    /// See https://datatracker.ietf.org/doc/html/rfc7873#section-5.3
+    pub fn validate_cookie(
+        &self,
+        client_cookie: &[u8; CLIENT_COOKIE_LEN],
+        required: bool,
+    ) -> Result<(), Error> {
+        let Some(cookie) = self.cookie() else {
+            if required {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "Response is missing the cookie",
+                ));
+            }
+            return Ok(());
+        };
+        if !cookie.starts_with(client_cookie) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "Response cookie does not match the query",
+            ));
+        }
+        let server_cookie_len = cookie.len() - CLIENT_COOKIE_LEN;
+        if (required || server_cookie_len > 0) && !SERVER_COOKIE_LEN.contains(&server_cookie_len) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "Invalid server cookie in response",
+            ));
+        }
+        Ok(())
+    }
+
+    /// The server cookie that follows the client cookie in responses.
+    pub fn server_cookie(&self) -> Option<&[u8]> {
+        self.options.iter().find_map(|x| match x {
+            EdnsOption::Cookie { server, .. } if !server.is_empty() => Some(server.as_slice()),
+            _ => None,
+        })
+    }
+}
+
+/// The cookies exchanged with each server: the client cookie we send it, the same
+/// for all our queries to it, and the last server cookie it returned. Once a server
+/// has returned one, its responses without a cookie are rejected. It can be shared
+/// between resolutions, as the config that holds it is cloned.
+#[derive(Debug, Default)]
+pub struct ServerCookies {
+    cookies: Mutex<HashMap<IpAddr, Cookies>>,
+}
+
+#[derive(Debug)]
+struct Cookies {
+    client: [u8; CLIENT_COOKIE_LEN],
+    server: Option<Vec<u8>>,
+}
+
+impl Cookies {
+    fn new() -> Self {
+        Cookies {
+            client: rand::random(),
+            server: None,
+        }
+    }
+}
+
+impl ServerCookies {
+    /// The client cookie to send to `server`, random the first time.
+    pub fn client_cookie(&self, server: IpAddr) -> [u8; CLIENT_COOKIE_LEN] {
+        self.cookies
+            .lock()
+            .unwrap()
+            .entry(server)
+            .or_insert_with(Cookies::new)
+            .client
+    }
+
+    /// The last server cookie returned by `server`.
+    pub fn get(&self, server: IpAddr) -> Option<Vec<u8>> {
+        self.cookies
+            .lock()
+            .unwrap()
+            .get(&server)
+            .and_then(|x| x.server.clone())
+    }
+
+    pub fn insert(&self, server: IpAddr, server_cookie: &[u8]) {
+        self.cookies
+            .lock()
+            .unwrap()
+            .entry(server)
+            .or_insert_with(Cookies::new)
+            .server = Some(server_cookie.to_vec());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::DNSRecord;
+
+    const CLIENT_COOKIE: [u8; CLIENT_COOKIE_LEN] = [1, 2, 3, 4, 5, 6, 7, 8];
+
+    fn response_opt(cookie: &[u8]) -> OptRecord {
+        let mut opt = OptRecord::new(1232);
+        opt.options
+            .push(EdnsOption::from_parts(COOKIE_OPTION_CODE, cookie));
+        opt
+    }
+
+    #[test]
+    fn cookie_round_trip() {
+        let bytes = OptRecord::new(1232).with_cookie(CLIENT_COOKIE).to_bytes();
+        let record = DNSRecord::from_reader(&mut Cursor::new(&bytes[..])).unwrap();
+        let opt = record.opt.unwrap();
+        assert_eq!(opt.cookie(), Some(CLIENT_COOKIE.to_vec()));
+        assert_eq!(opt.server_cookie(), None);
+        assert_eq!(
+            opt.options,
+            vec![EdnsOption::Cookie {
+                client: CLIENT_COOKIE,
+                server: vec![],
+            }]
+        );
+    }
+
+    #[test]
+    fn accepts_echoed_cookie() {
+        let cookie = [&CLIENT_COOKIE[..], &[9; 8]].concat();
+        let opt = response_opt(&cookie);
+        assert!(opt.validate_cookie(&CLIENT_COOKIE, true).is_ok());
+        assert_eq!(opt.server_cookie(), Some(&[9; 8][..]));
+    }
+
+    #[test]
+    fn rejects_altered_cookie() {
+        let mut cookie = [&CLIENT_COOKIE[..], &[9; 8]].concat();
+        cookie[0] ^= 0xff;
+        assert!(response_opt(&cookie)
+            .validate_cookie(&CLIENT_COOKIE, false)
+            .is_err());
+    }
+
+    #[test]
+    fn rejects_server_cookie_of_invalid_length() {
+        let cookie = [&CLIENT_COOKIE[..], &[9; 4]].concat();
+        assert!(response_opt(&cookie)
+            .validate_cookie(&CLIENT_COOKIE, false)
+            .is_err());
+    }
+
+    #[test]
+    fn missing_cookie_is_only_rejected_when_required() {
+        let opt = OptRecord::new(1232);
+        assert!(opt.validate_cookie(&CLIENT_COOKIE, false).is_ok());
+        assert!(opt.validate_cookie(&CLIENT_COOKIE, true).is_err());
+    }
+
+    #[test]
+    fn nsid_round_trip() {
+        let request = OptRecord::new(1232).with_nsid_request().to_bytes();
+        let record = DNSRecord::from_reader(&mut Cursor::new(&request[..])).unwrap();
+        assert_eq!(record.opt.unwrap().nsid(), Some(vec![]));
+
+        let mut response = OptRecord::new(1232);
+        response
+            .options
+            .push(EdnsOption::Nsid(b"anycast-ams-1".to_vec()));
+        let bytes = response.to_bytes();
+        let record = DNSRecord::from_reader(&mut Cursor::new(&bytes[..])).unwrap();
+        assert_eq!(record.opt.unwrap().nsid(), Some(b"anycast-ams-1".to_vec()));
+
+        assert_eq!(OptRecord::new(1232).nsid(), None);
+    }
+
+    #[test]
+    fn typed_cookie_and_nsid_options() {
+        let cookie = [&CLIENT_COOKIE[..], &[9; 8]].concat();
+        let mut opt = response_opt(&cookie);
+        opt.options.push(EdnsOption::Nsid(b"ns1".to_vec()));
+        opt.options.push(EdnsOption::Unknown(65001, vec![1, 2]));
+        let bytes = opt.to_bytes();
+        let record = DNSRecord::from_reader(&mut Cursor::new(&bytes[..])).unwrap();
+
+        assert_eq!(
+            record.opt.unwrap().options,
+            vec![
+                EdnsOption::Cookie {
+                    client: CLIENT_COOKIE,
+                    server: vec![9; 8],
+                },
+                EdnsOption::Nsid(b"ns1".to_vec()),
+                EdnsOption::Unknown(65001, vec![1, 2]),
+            ]
+        );
+    }
+
+    #[test]
+    fn short_option_is_kept_unknown() {
+        let option = EdnsOption::from_parts(COOKIE_OPTION_CODE, &[1, 2, 3]);
+        assert_eq!(
+            option,
+            EdnsOption::Unknown(COOKIE_OPTION_CODE, vec![1, 2, 3])
+        );
+        assert_eq!(option.code(), COOKIE_OPTION_CODE);
+    }
+
+    #[test]
+    fn client_subnet_round_trip() {
+        let option = EdnsOption::ClientSubnet {
+            family: 1,
+            source_prefix_len: 24,
+            scope_prefix_len: 0,
+            address: vec![192, 0, 2],
+        };
+        assert_eq!(option.data(), [0, 1, 24, 0, 192, 0, 2]);
+        assert_eq!(
+            EdnsOption::from_parts(option.code(), &option.data()),
+            option
+        );
+    }
+}
@@ -0,0 +1,228 @@
+use std::fmt;
+use std::io::ErrorKind;
+use std::net::IpAddr;
+
+use crate::TypeField;
+
+/// Parse errors that tell where in the message they happened, as an offset from
+/// its start, and the ways a resolution can end without an answer. They are
+/// returned wrapped in an `io::Error`, see `DnsError::from_io`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DnsError {
+    /// a TYPE we don't know about
+    InvalidType { value: u16, offset: u64 },
+    /// a CLASS we don't know about
+    InvalidClass { value: u16, offset: u64 },
+    /// a compression pointer outside of the message
+    BadPointer { pointer: u16, offset: u64 },
+    /// too many compression pointers in a row, they are likely in a loop
+    RecursionDepthExceeded { offset: u64 },
+    /// the rdata length goes past the end of the message
+    RdataOverflow { length: u16, offset: u64 },
+    /// the rdata of a record is shorter or longer than its RDLENGTH
+    RdataLength {
+        type_field: TypeField,
+        length: u16,
+        offset: u64,
+    },
+    /// two dots in a row, or a dot at the start of a name
+    EmptyLabel,
+    /// a backslash at `offset` of a name that isn't followed by a character or by
+    /// 3 digits of a value up to 255
+    InvalidEscape { offset: usize },
+    /// a label longer than 63 bytes
+    LabelTooLong { length: usize },
+    /// a name longer than 255 bytes in wire format
+    NameTooLong { length: usize },
+    /// the name doesn't exist
+    NXDomain { name: String },
+    /// the name exists but has no records of the type asked for
+    NoData { name: String, type_field: TypeField },
+    /// none of the name servers of a delegation gave a valid response
+    Unreachable { name_servers: Vec<IpAddr> },
+    /// too many CNAMEs in a row, they are likely in a loop
+    CnameChainTooLong { name: String },
+    /// too many referrals, or name servers without glue whose resolution needs
+    /// the resolution of other such name servers, they are likely in a loop
+    DelegationTooDeep { name: String },
+}
+impl fmt::Display for DnsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DnsError::InvalidType { value, offset } => {
+                write!(f, "Invalid TYPE field {} at offset {}", value, offset)
+            }
+            DnsError::InvalidClass { value, offset } => {
+                write!(f, "Invalid CLASS field {} at offset {}", value, offset)
+            }
+            DnsError::BadPointer { pointer, offset } => write!(
+                f,
+                "Compression pointer to {} out of bounds at offset {}",
+                pointer, offset
+            ),
+            DnsError::RecursionDepthExceeded { offset } => {
+                write!(f, "Recursion depth exceeded at offset {}", offset)
+            }
+            DnsError::RdataOverflow { length, offset } => write!(
+                f,
+                "Record data of {} bytes overflows the packet at offset {}",
+                length, offset
+            ),
+            DnsError::RdataLength {
+                type_field,
+                length,
+                offset,
+            } => write!(
+                f,
+                "{} record data at offset {} doesn't match its length of {} bytes",
+                type_field, offset, length
+            ),
+            DnsError::EmptyLabel => write!(f, "Empty label in domain name"),
+            DnsError::InvalidEscape { offset } => {
+                write!(f, "Invalid escape at offset {} of domain name", offset)
+            }
+            DnsError::LabelTooLong { length } => {
+                write!(f, "Label of {} bytes is longer than 63", length)
+            }
+            DnsError::NameTooLong { length } => {
+                write!(f, "Domain name of {} bytes is longer than 255", length)
+            }
+            DnsError::NXDomain { name } => write!(f, "Domain name {} doesn't exist", name),
+            DnsError::NoData { name, type_field } => {
+                write!(f, "No {} records for {}", type_field, name)
+            }
+            DnsError::Unreachable { name_servers } => {
+                write!(f, "No valid response from name servers {:?}", name_servers)
+            }
+            DnsError::CnameChainTooLong { name } => {
+                write!(f, "CNAME chain too long at {}", name)
+            }
+            DnsError::DelegationTooDeep { name } => {
+                write!(f, "Delegation too deep while resolving {}", name)
+            }
+        }
+    }
+}
+impl std::error::Error for DnsError {}
+impl From<DnsError> for std::io::Error {
+    fn from(error: DnsError) -> Self {
+        let kind = match error {
+            DnsError::RdataOverflow { .. } => ErrorKind::UnexpectedEof,
+            DnsError::NXDomain { .. } | DnsError::NoData { .. } => ErrorKind::NotFound,
+            DnsError::Unreachable { .. } => ErrorKind::HostUnreachable,
+            DnsError::CnameChainTooLong { .. } | DnsError::DelegationTooDeep { .. } => {
+                ErrorKind::Other
+            }
+            _ => ErrorKind::InvalidData,
+        };
+        std::io::Error::new(kind, error)
+    }
+}
+impl DnsError {
+    /// The `DnsError` wrapped in `error`, if it is one, possibly within a
+    /// `ResolutionError`.
+    pub fn from_io(error: &std::io::Error) -> Option<&DnsError> {
+        let inner = error.get_ref()?;
+        match inner.downcast_ref::<ResolutionError>() {
+            Some(resolution_error) => DnsError::from_io(&resolution_error.error),
+            None => inner.downcast_ref(),
+        }
+    }
+}
+
+/// A failed resolution along with the name servers that responded on the way, in
+/// the order they were queried. It is returned wrapped in an `io::Error` of the
+/// same kind as `error`, see `ResolutionError::from_io`.
+#[derive(Debug)]
+pub struct ResolutionError {
+    pub error: std::io::Error,
+    pub path: Vec<IpAddr>,
+}
+impl fmt::Display for ResolutionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}, ", self.error)?;
+        if self.path.is_empty() {
+            return write!(f, "no name server responded");
+        }
+        write!(f, "delegation path: ")?;
+        for (i, name_server) in self.path.iter().enumerate() {
+            if i > 0 {
+                write!(f, " -> ")?;
+            }
+            write!(f, "{}", name_server)?;
+        }
+        Ok(())
+    }
+}
+impl std::error::Error for ResolutionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+impl From<ResolutionError> for std::io::Error {
+    fn from(error: ResolutionError) -> Self {
+        std::io::Error::new(error.error.kind(), error)
+    }
+}
+impl ResolutionError {
+    /// The `ResolutionError` wrapped in `error`, if it is one.
+    pub fn from_io(error: &std::io::Error) -> Option<&ResolutionError> {
+        error.get_ref().and_then(|x| x.downcast_ref())
+    }
+
+    /// The last name server that responded before the resolution failed.
+    pub fn last_server(&self) -> Option<IpAddr> {
+        self.path.last().copied()
+    }
+}
+
+/// Copies `error`, along with the `DnsError` or `ResolutionError` it wraps so that
+/// their `from_io` still find them. Other errors only keep their kind and message.
+pub fn clone_io_error(error: &std::io::Error) -> std::io::Error {
+    let Some(inner) = error.get_ref() else {
+        return error.kind().into();
+    };
+    if let Some(dns_error) = inner.downcast_ref::<DnsError>() {
+        return std::io::Error::new(error.kind(), dns_error.clone());
+    }
+    if let Some(resolution_error) = inner.downcast_ref::<ResolutionError>() {
+        let resolution_error = ResolutionError {
+            error: clone_io_error(&resolution_error.error),
+            path: resolution_error.path.clone(),
+        };
+        return std::io::Error::new(error.kind(), resolution_error);
+    }
+    std::io::Error::new(error.kind(), inner.to_string())
+}
+
+/// Issues found by `DNSPacket::from_lenient` that didn't stop it from parsing the
+/// rest of the packet.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Warning {
+    /// a question or record of a TYPE we don't know about was skipped
+    UnknownType { value: u16, offset: u64 },
+    /// a question or record of a CLASS we don't know about was skipped
+    UnknownClass { value: u16, offset: u64 },
+    /// bytes left after the last record
+    TrailingBytes { count: usize },
+    /// the reserved Z bit of the header is set, `z` holds all 3 Z bits
+    ReservedFlag { z: u8 },
+}
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Warning::UnknownType { value, offset } => {
+                write!(f, "Skipped unknown TYPE {} at offset {}", value, offset)
+            }
+            Warning::UnknownClass { value, offset } => {
+                write!(f, "Skipped unknown CLASS {} at offset {}", value, offset)
+            }
+            Warning::TrailingBytes { count } => {
+                write!(f, "{} bytes after the last record", count)
+            }
+            Warning::ReservedFlag { z } => {
+                write!(f, "Reserved Z bit set in the header flags ({:03b})", z)
+            }
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use std::fmt;
+use std::io::{Cursor, Error, ErrorKind};
+
+use crate::reader::read_bytes;
+
+/// An EUI-48 or EUI-64 address, e.g. the MAC address of a device.
+/// See https://datatracker.ietf.org/doc/html/rfc7043#section-3
+#[derive(Debug, Clone, PartialEq)]
+pub struct EuiRecord<const N: usize> {
+    pub address: [u8; N],
+}
+pub type Eui48Record = EuiRecord<6>;
+pub type Eui64Record = EuiRecord<8>;
+impl<const N: usize> EuiRecord<N> {
+    /// Reads the rdata starting at the current position of the reader, which must
+    /// be exactly the `N` bytes of the address.
+    pub fn from_reader(reader: &mut Cursor<&[u8]>, data_len: u16) -> Result<Self, std::io::Error> {
+        if data_len as usize != N {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("EUI rdata must be {} bytes long", N),
+            ));
+        }
+        let address = read_bytes(reader, N)?.try_into().unwrap();
+        Ok(EuiRecord { address })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.address.to_vec()
+    }
+}
+impl<const N: usize> fmt::Display for EuiRecord<N> {
+    /// Formats the address as colon separated hex bytes, e.g. `00:00:5e:00:53:2a`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, byte) in self.address.iter().enumerate() {
+            if i > 0 {
+                write!(f, ":")?;
+            }
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{message, record};
+    use crate::{DNSPacket, TypeField};
+
+    #[test]
+    fn decodes_eui48() {
+        let rdata = [0x00, 0x00, 0x5e, 0x00, 0x53, 0x2a];
+        let data = message(
+            [0, 1, 0, 0],
+            &record("host.example.com", TypeField::EUI48 as u16, &rdata),
+        );
+        let packet = DNSPacket::from(&data).unwrap();
+        let eui48 = packet.answers[0].eui48.as_ref().unwrap();
+        assert_eq!(eui48.address, rdata);
+        assert_eq!(eui48.to_string(), "00:00:5e:00:53:2a");
+    }
+
+    #[test]
+    fn decodes_eui64() {
+        let rdata = [0x00, 0x00, 0x5e, 0xef, 0x10, 0x00, 0x00, 0x2a];
+        let data = message(
+            [0, 1, 0, 0],
+            &record("host.example.com", TypeField::EUI64 as u16, &rdata),
+        );
+        let packet = DNSPacket::from(&data).unwrap();
+        let eui64 = packet.answers[0].eui64.as_ref().unwrap();
+        assert_eq!(eui64.address, rdata);
+        assert_eq!(eui64.to_string(), "00:00:5e:ef:10:00:00:2a");
+    }
+
+    #[test]
+    fn rejects_wrong_length() {
+        let data = message(
+            [0, 1, 0, 0],
+            &record("host.example.com", TypeField::EUI48 as u16, &[0; 8]),
+        );
+        assert!(DNSPacket::from(&data).is_err());
+    }
+}
@@ -0,0 +1,213 @@
+use std::fmt;
+
+/// The second 16 bits of the header.
+///
+/// ```text
+///   0  1  2  3  4  5  6  7  8  9 10 11 12 13 14 15
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// |QR|   Opcode  |AA|TC|RD|RA|   Z    |   RCODE   |
+/// +--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+--+
+/// ```
+///
+/// See https://datatracker.ietf.org/doc/html/rfc1035#section-4.1.1
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct DNSFlags(u16);
+
+const QR: u16 = 0b1000_0000_0000_0000;
+const OPCODE_SHIFT: u16 = 11;
+const OPCODE_MASK: u16 = 0b0111_1000_0000_0000;
+const AA: u16 = 0b0000_0100_0000_0000;
+const TC: u16 = 0b0000_0010_0000_0000;
+const RD: u16 = 0b0000_0001_0000_0000;
+const Z_SHIFT: u16 = 4;
+const Z_MASK: u16 = 0b0000_0000_0111_0000;
+/// the only Z bit that hasn't been assigned a meaning since RFC 1035, AD and CD
+/// took the other two, see https://datatracker.ietf.org/doc/html/rfc4035#section-3.2
+const Z_RESERVED: u16 = 0b0000_0000_0100_0000;
+/// Checking Disabled, see https://datatracker.ietf.org/doc/html/rfc4035#section-3.2.2
+const CD: u16 = 0b0000_0000_0001_0000;
+const RCODE_MASK: u16 = 0b0000_0000_0000_1111;
+
+/// a standard query
+pub const OPCODE_QUERY: u8 = 0;
+/// an inverse query (Obsolete), see https://datatracker.ietf.org/doc/html/rfc3425
+pub const OPCODE_IQUERY: u8 = 1;
+/// a server status request
+pub const OPCODE_STATUS: u8 = 2;
+/// a zone change notification, see https://datatracker.ietf.org/doc/html/rfc1996
+pub const OPCODE_NOTIFY: u8 = 4;
+/// a dynamic update, see https://datatracker.ietf.org/doc/html/rfc2136
+pub const OPCODE_UPDATE: u8 = 5;
+
+/// The kind of query, the 4 opcode bits of the flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpCode {
+    Query,
+    IQuery,
+    Status,
+    Notify,
+    Update,
+    Unknown(u8),
+}
+impl From<u8> for OpCode {
+    fn from(value: u8) -> Self {
+        match value {
+            OPCODE_QUERY => OpCode::Query,
+            OPCODE_IQUERY => OpCode::IQuery,
+            OPCODE_STATUS => OpCode::Status,
+            OPCODE_NOTIFY => OpCode::Notify,
+            OPCODE_UPDATE => OpCode::Update,
+            _ => OpCode::Unknown(value),
+        }
+    }
+}
+impl From<OpCode> for u8 {
+    fn from(opcode: OpCode) -> Self {
+        match opcode {
+            OpCode::Query => OPCODE_QUERY,
+            OpCode::IQuery => OPCODE_IQUERY,
+            OpCode::Status => OPCODE_STATUS,
+            OpCode::Notify => OPCODE_NOTIFY,
+            OpCode::Update => OPCODE_UPDATE,
+            OpCode::Unknown(value) => value,
+        }
+    }
+}
+impl fmt::Display for OpCode {
+    /// Formats the opcode like `dig` does, e.g. `QUERY`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OpCode::Query => write!(f, "QUERY"),
+            OpCode::IQuery => write!(f, "IQUERY"),
+            OpCode::Status => write!(f, "STATUS"),
+            OpCode::Notify => write!(f, "NOTIFY"),
+            OpCode::Update => write!(f, "UPDATE"),
+            OpCode::Unknown(value) => write!(f, "RESERVED{}", value),
+        }
+    }
+}
+
+/// the domain name in the query doesn't exist
+pub const RCODE_NXDOMAIN: u8 = 3;
+
+impl DNSFlags {
+    pub fn from_bits(bits: u16) -> Self {
+        DNSFlags(bits)
+    }
+
+    pub fn bits(self) -> u16 {
+        self.0
+    }
+
+    fn with_bit(self, bit: u16, value: bool) -> Self {
+        if value {
+            DNSFlags(self.0 | bit)
+        } else {
+            DNSFlags(self.0 & !bit)
+        }
+    }
+
+    /// whether this message is a response (true) or a query (false)
+    pub fn is_response(self) -> bool {
+        self.0 & QR != 0
+    }
+
+    pub fn with_response(self, value: bool) -> Self {
+        self.with_bit(QR, value)
+    }
+
+    /// the kind of query, set by the originator and copied into the response
+    pub fn opcode(self) -> OpCode {
+        OpCode::from(((self.0 & OPCODE_MASK) >> OPCODE_SHIFT) as u8)
+    }
+
+    pub fn with_opcode(self, opcode: OpCode) -> Self {
+        let opcode = (u16::from(u8::from(opcode)) << OPCODE_SHIFT) & OPCODE_MASK;
+        DNSFlags((self.0 & !OPCODE_MASK) | opcode)
+    }
+
+    /// whether the responding name server is an authority for the domain name in question
+    pub fn is_authoritative(self) -> bool {
+        self.0 & AA != 0
+    }
+
+    pub fn with_authoritative(self, value: bool) -> Self {
+        self.with_bit(AA, value)
+    }
+
+    /// whether the message was truncated because it didn't fit the transport
+    pub fn is_truncated(self) -> bool {
+        self.0 & TC != 0
+    }
+
+    pub fn with_truncated(self, value: bool) -> Self {
+        self.with_bit(TC, value)
+    }
+
+    /// whether the server should pursue the query recursively
+    pub fn recursion_desired(self) -> bool {
+        self.0 & RD != 0
+    }
+
+    pub fn with_recursion_desired(self, value: bool) -> Self {
+        self.with_bit(RD, value)
+    }
+
+    /// the 3 bits RFC 1035 reserved for future use, which include the AD and CD
+    /// bits of DNSSEC
+    pub fn z(self) -> u8 {
+        ((self.0 & Z_MASK) >> Z_SHIFT) as u8
+    }
+
+    /// whether the Z bit that is still reserved is set, it must be zero but
+    /// some middleboxes set it
+    pub fn has_reserved_bit(self) -> bool {
+        self.0 & Z_RESERVED != 0
+    }
+
+    /// whether a validating resolver should return the data even if DNSSEC
+    /// validation fails, leaving the checks to us
+    pub fn checking_disabled(self) -> bool {
+        self.0 & CD != 0
+    }
+
+    pub fn with_checking_disabled(self, value: bool) -> Self {
+        self.with_bit(CD, value)
+    }
+
+    /// the response code, 0 means no error
+    pub fn rcode(self) -> u8 {
+        (self.0 & RCODE_MASK) as u8
+    }
+
+    pub fn with_rcode(self, rcode: u8) -> Self {
+        DNSFlags((self.0 & !RCODE_MASK) | (u16::from(rcode) & RCODE_MASK))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opcode_round_trip() {
+        let flags = DNSFlags::default()
+            .with_response(true)
+            .with_opcode(OpCode::Notify);
+        assert_eq!(flags.bits(), 0xa000);
+        assert_eq!(flags.opcode(), OpCode::Notify);
+        assert!(flags.is_response());
+        assert_eq!(flags.with_opcode(OpCode::Query).bits(), 0x8000);
+        assert_eq!(DNSFlags::from_bits(0x7800).opcode(), OpCode::Unknown(15));
+    }
+
+    #[test]
+    fn decodes_update_opcode() {
+        // opcode 5 in bits 11-14
+        let flags = DNSFlags::from_bits(5 << 11);
+        assert_eq!(flags.opcode(), OpCode::Update);
+        assert_eq!(u8::from(OpCode::Update), 5);
+        assert_eq!(OpCode::from(2), OpCode::Status);
+        assert_eq!(OpCode::from(3), OpCode::Unknown(3));
+    }
+}
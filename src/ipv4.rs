@@ -1,5 +1,10 @@
+use std::io::{Error, ErrorKind};
 use std::net::Ipv4Addr;
 
-pub fn ipv4_addr_from_bytes(chunk: &[u8]) -> Ipv4Addr {
-    Ipv4Addr::new(chunk[0], chunk[1], chunk[2], chunk[3])
+/// The address of an A record, whose rdata is exactly 4 bytes.
+pub fn ipv4_addr_from_bytes(data: &[u8]) -> Result<Ipv4Addr, std::io::Error> {
+    let octets: [u8; 4] = data
+        .try_into()
+        .map_err(|_| Error::new(ErrorKind::InvalidData, "A rdata must be 4 bytes long"))?;
+    Ok(Ipv4Addr::from(octets))
 }
@@ -1,14 +1,10 @@
+use std::io::{Error, ErrorKind};
 use std::net::Ipv6Addr;
 
-pub fn ipv6_addr_from_bytes(chunk: &[u8]) -> Ipv6Addr {
-    Ipv6Addr::new(
-        u16::from_be_bytes([chunk[0], chunk[1]]),
-        u16::from_be_bytes([chunk[2], chunk[3]]),
-        u16::from_be_bytes([chunk[4], chunk[5]]),
-        u16::from_be_bytes([chunk[6], chunk[7]]),
-        u16::from_be_bytes([chunk[8], chunk[9]]),
-        u16::from_be_bytes([chunk[10], chunk[11]]),
-        u16::from_be_bytes([chunk[12], chunk[13]]),
-        u16::from_be_bytes([chunk[14], chunk[15]]),
-    )
+/// The address of an AAAA record, whose rdata is exactly 16 bytes.
+pub fn ipv6_addr_from_bytes(data: &[u8]) -> Result<Ipv6Addr, std::io::Error> {
+    let octets: [u8; 16] = data
+        .try_into()
+        .map_err(|_| Error::new(ErrorKind::InvalidData, "AAAA rdata must be 16 bytes long"))?;
+    Ok(Ipv6Addr::from(octets))
 }
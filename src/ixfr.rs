@@ -0,0 +1,255 @@
+use std::io::{Error, ErrorKind};
+use std::net::{Ipv4Addr, TcpStream};
+
+use crate::tcp::{write_message, TcpMessageReader};
+use crate::{
+    ClassField, DNSFlags, DNSHeader, DNSPacket, DNSQuestion, DNSRecord, DomainName, QType,
+    SoaRecord, TypeField, DEFAULT_DNS_PORT, QUERY_TIMEOUT,
+};
+
+/// The records deleted from and added to a zone to go from one serial to the next.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZoneChange {
+    pub from_serial: u32,
+    pub to_serial: u32,
+    pub deleted: Vec<DNSRecord>,
+    pub added: Vec<DNSRecord>,
+}
+
+enum Section {
+    Deleted,
+    Added,
+}
+
+/// Collects the changes of an IXFR response, which can span many messages.
+///
+/// The answers start and end with the SOA of the current version of the zone. In
+/// between, every change is the SOA of the old version followed by the deleted
+/// records, and the SOA of the new version followed by the added ones.
+/// See https://datatracker.ietf.org/doc/html/rfc1995#section-4
+#[derive(Default)]
+struct IxfrCollector {
+    current_serial: Option<u32>,
+    change: Option<(ZoneChange, Section)>,
+    changes: Vec<ZoneChange>,
+    done: bool,
+}
+impl IxfrCollector {
+    fn push(&mut self, record: DNSRecord, serial: u32) -> Result<(), std::io::Error> {
+        if self.done {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "Records after the end of the zone transfer",
+            ));
+        }
+        let soa_serial = record.soa.as_ref().map(|x| x.serial);
+        let Some(current_serial) = self.current_serial else {
+            let current_serial = soa_serial.ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    "IXFR response must start with an SOA",
+                )
+            })?;
+            self.current_serial = Some(current_serial);
+            // a single SOA means we are already up to date
+            self.done = current_serial == serial;
+            return Ok(());
+        };
+
+        match (self.change.take(), soa_serial) {
+            (None, Some(from_serial)) => {
+                self.done = from_serial == current_serial;
+                self.start_change(from_serial);
+            }
+            (None, None) => {
+                return Err(Error::new(
+                    ErrorKind::Unsupported,
+                    "Server answered with a full zone transfer",
+                ));
+            }
+            (Some((mut change, Section::Deleted)), Some(to_serial)) => {
+                change.to_serial = to_serial;
+                self.change = Some((change, Section::Added));
+            }
+            (Some((mut change, Section::Deleted)), None) => {
+                change.deleted.push(record);
+                self.change = Some((change, Section::Deleted));
+            }
+            (Some((change, Section::Added)), Some(from_serial)) => {
+                self.changes.push(change);
+                self.done = from_serial == current_serial;
+                self.start_change(from_serial);
+            }
+            (Some((mut change, Section::Added)), None) => {
+                change.added.push(record);
+                self.change = Some((change, Section::Added));
+            }
+        }
+        Ok(())
+    }
+
+    fn start_change(&mut self, from_serial: u32) {
+        if self.done {
+            return;
+        }
+        let change = ZoneChange {
+            from_serial,
+            to_serial: from_serial,
+            deleted: vec![],
+            added: vec![],
+        };
+        self.change = Some((change, Section::Deleted));
+    }
+}
+
+/// Builds an IXFR query for the changes to `zone` since `serial`, which is sent
+/// in an SOA record of the authority section.
+pub fn build_ixfr(zone: &DomainName, serial: u32) -> Vec<u8> {
+    let header = DNSHeader {
+        id: rand::random::<u16>(),
+        flags: DNSFlags::default(),
+        num_questions: 0,
+        num_answers: 0,
+        num_authorities: 0,
+        num_additionals: 0,
+    };
+    let question = DNSQuestion {
+        name: zone.clone(),
+        type_field: QType::IXFR,
+        class: ClassField::IN,
+    };
+    let soa = SoaRecord {
+        mname: DomainName::from("."),
+        rname: DomainName::from("."),
+        serial,
+        refresh: 0,
+        retry: 0,
+        expire: 0,
+        minimum: 0,
+    };
+    // the rdata has no compressed names, so it always parses back
+    let authority = DNSRecord::new(
+        zone.clone(),
+        TypeField::SOA,
+        ClassField::IN,
+        0,
+        &soa.to_bytes(),
+    )
+    .unwrap();
+    DNSPacket::new(header, vec![question], vec![], vec![authority], vec![]).to_bytes()
+}
+
+/// Asks `server` over TCP for the changes to `zone` since `serial`, in order.
+/// An empty list means the zone didn't change.
+pub fn ixfr(
+    server: Ipv4Addr,
+    zone: &DomainName,
+    serial: u32,
+) -> Result<Vec<ZoneChange>, std::io::Error> {
+    let mut stream = TcpStream::connect_timeout(&(server, DEFAULT_DNS_PORT).into(), QUERY_TIMEOUT)?;
+    stream.set_read_timeout(Some(QUERY_TIMEOUT))?;
+    let query = build_ixfr(zone, serial);
+    let id = u16::from_be_bytes([query[0], query[1]]);
+    write_message(&mut stream, &query)?;
+
+    let mut collector = IxfrCollector::default();
+    for packet in TcpMessageReader::new(stream) {
+        let packet = packet?;
+        if packet.header.id != id {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "Response id doesn't match the query",
+            ));
+        }
+        if packet.header.flags.rcode() != 0 {
+            return Err(Error::other(format!(
+                "Zone transfer failed with RCODE {}",
+                packet.header.flags.rcode()
+            )));
+        }
+        for record in packet.answers {
+            collector.push(record, serial)?;
+        }
+        if collector.done {
+            return Ok(collector.changes);
+        }
+    }
+    Err(Error::new(
+        ErrorKind::UnexpectedEof,
+        "Connection closed before the end of the zone transfer",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{a, rr};
+
+    fn soa(serial: u32) -> DNSRecord {
+        let soa = SoaRecord {
+            mname: DomainName::from("ns1.example.com"),
+            rname: DomainName::from("hostmaster.example.com"),
+            serial,
+            refresh: 7200,
+            retry: 3600,
+            expire: 1209600,
+            minimum: 300,
+        };
+        rr("example.com", TypeField::SOA, &soa.to_bytes())
+    }
+
+    fn collect(records: Vec<DNSRecord>, serial: u32) -> Result<IxfrCollector, Error> {
+        let mut collector = IxfrCollector::default();
+        for record in records {
+            collector.push(record, serial)?;
+        }
+        Ok(collector)
+    }
+
+    #[test]
+    fn collects_one_delete_and_one_add() {
+        let deleted = a("www.example.com", [192, 0, 2, 1].into());
+        let added = a("www.example.com", [192, 0, 2, 2].into());
+        let records = vec![
+            soa(2),
+            soa(1),
+            deleted.clone(),
+            soa(2),
+            added.clone(),
+            soa(2),
+        ];
+        let collector = collect(records, 1).unwrap();
+        assert!(collector.done);
+        assert_eq!(
+            collector.changes,
+            vec![ZoneChange {
+                from_serial: 1,
+                to_serial: 2,
+                deleted: vec![deleted],
+                added: vec![added],
+            }]
+        );
+    }
+
+    #[test]
+    fn single_soa_means_up_to_date() {
+        let collector = collect(vec![soa(1)], 1).unwrap();
+        assert!(collector.done);
+        assert!(collector.changes.is_empty());
+        assert!(collect(vec![soa(1), soa(1)], 1).is_err());
+    }
+
+    #[test]
+    fn rejects_full_zone_transfer() {
+        let records = vec![soa(2), a("www.example.com", [192, 0, 2, 1].into())];
+        let error = collect(records, 1).err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::Unsupported);
+    }
+
+    #[test]
+    fn builds_ixfr_query() {
+        let query = DNSPacket::from(&build_ixfr(&DomainName::from("example.com"), 7)).unwrap();
+        assert_eq!(query.questions[0].type_field, QType::IXFR);
+        assert_eq!(query.authorities[0].soa.as_ref().unwrap().serial, 7);
+    }
+}
@@ -0,0 +1,67 @@
+use std::io::Cursor;
+
+use crate::reader::read_u16;
+use crate::DomainName;
+
+/// A host willing to act as a key exchanger for the owner name, e.g. to negotiate
+/// IPsec keys on its behalf.
+/// See https://datatracker.ietf.org/doc/html/rfc2230#section-3
+#[derive(Debug, Clone, PartialEq)]
+pub struct KxRecord {
+    /// lower values are preferred
+    pub preference: u16,
+    /// the host acting as a key exchanger
+    pub exchanger: DomainName,
+}
+impl KxRecord {
+    pub fn from_reader(reader: &mut Cursor<&[u8]>) -> Result<Self, std::io::Error> {
+        Ok(KxRecord {
+            preference: read_u16(reader)?,
+            exchanger: DomainName::from_reader(reader)?,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.preference.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&self.exchanger.to_bytes());
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{message, record};
+    use crate::{DNSPacket, TypeField};
+
+    #[test]
+    fn decodes_kx_rdata() {
+        let rdata = KxRecord {
+            preference: 10,
+            exchanger: DomainName::from("kx.example.com"),
+        }
+        .to_bytes();
+        let data = message(
+            [0, 1, 0, 0],
+            &record("example.com", TypeField::KX as u16, &rdata),
+        );
+        let packet = DNSPacket::from(&data).unwrap();
+        let kx = packet.answers[0].kx.as_ref().unwrap();
+        assert_eq!(kx.preference, 10);
+        assert_eq!(kx.exchanger.string, "kx.example.com");
+    }
+
+    #[test]
+    fn decodes_compressed_exchanger() {
+        // the exchanger is kx + a pointer to the owner name, right after the header
+        let rdata = [0, 20, 2, b'k', b'x', 0xc0, 12];
+        let data = message(
+            [0, 1, 0, 0],
+            &record("example.com", TypeField::KX as u16, &rdata),
+        );
+        let packet = DNSPacket::from(&data).unwrap();
+        let kx = packet.answers[0].kx.as_ref().unwrap();
+        assert_eq!(kx.preference, 20);
+        assert_eq!(kx.exchanger.string, "kx.example.com");
+    }
+}
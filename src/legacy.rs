@@ -0,0 +1,236 @@
+//! Experimental and obsolete record types that are rarely seen outside of
+//! conformance suites and old zones.
+//! See https://datatracker.ietf.org/doc/html/rfc1183#section-3
+
+use std::io::{Cursor, Error, ErrorKind};
+
+#[cfg(feature = "obsolete-types")]
+use crate::reader::read_bytes;
+use crate::reader::{read_character_string, read_u16};
+use crate::DomainName;
+
+fn character_string_bytes(string: &str) -> Vec<u8> {
+    let mut bytes = vec![string.len() as u8];
+    bytes.extend_from_slice(string.as_bytes());
+    bytes
+}
+
+/// The X.25 address of the owner name on a public switched data network.
+/// See https://datatracker.ietf.org/doc/html/rfc1183#section-3.1
+#[derive(Debug, Clone, PartialEq)]
+pub struct X25Record {
+    /// at least 4 decimal digits, the country code first
+    pub psdn_address: String,
+}
+impl X25Record {
+    pub fn from_reader(reader: &mut Cursor<&[u8]>) -> Result<Self, std::io::Error> {
+        let psdn_address = read_character_string(reader)?;
+        Ok(X25Record {
+            psdn_address: String::from_utf8_lossy(&psdn_address).into_owned(),
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        character_string_bytes(&self.psdn_address)
+    }
+}
+
+/// The ISDN number of the owner name.
+/// See https://datatracker.ietf.org/doc/html/rfc1183#section-3.2
+#[derive(Debug, Clone, PartialEq)]
+pub struct IsdnRecord {
+    /// the telephone number, country code first
+    pub address: String,
+    /// the subaddress, as hexadecimal digits
+    pub subaddress: Option<String>,
+}
+impl IsdnRecord {
+    /// Reads the rdata starting at the current position of the reader, `data_len`
+    /// bytes long.
+    pub fn from_reader(reader: &mut Cursor<&[u8]>, data_len: u16) -> Result<Self, std::io::Error> {
+        let end = reader.position() + u64::from(data_len);
+        let address = read_character_string(reader)?;
+        let subaddress = if reader.position() < end {
+            Some(read_character_string(reader)?)
+        } else {
+            None
+        };
+        if reader.position() != end {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "ISDN strings don't match the rdata length",
+            ));
+        }
+        Ok(IsdnRecord {
+            address: String::from_utf8_lossy(&address).into_owned(),
+            subaddress: subaddress.map(|x| String::from_utf8_lossy(&x).into_owned()),
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = character_string_bytes(&self.address);
+        if let Some(subaddress) = &self.subaddress {
+            bytes.extend_from_slice(&character_string_bytes(subaddress));
+        }
+        bytes
+    }
+}
+
+/// A host that routes packets to the owner name, for hosts without a direct
+/// wide area network address.
+/// See https://datatracker.ietf.org/doc/html/rfc1183#section-3.3
+#[derive(Debug, Clone, PartialEq)]
+pub struct RtRecord {
+    /// lower values are preferred
+    pub preference: u16,
+    pub intermediate_host: DomainName,
+}
+impl RtRecord {
+    pub fn from_reader(reader: &mut Cursor<&[u8]>) -> Result<Self, std::io::Error> {
+        Ok(RtRecord {
+            preference: read_u16(reader)?,
+            intermediate_host: DomainName::from_reader(reader)?,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.preference.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&self.intermediate_host.to_bytes());
+        bytes
+    }
+}
+
+/// The position of the owner name, superseded by LOC records.
+/// See https://datatracker.ietf.org/doc/html/rfc1712#section-3
+#[cfg(feature = "obsolete-types")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct GposRecord {
+    /// decimal degrees, negative to the west
+    pub longitude: String,
+    /// decimal degrees, negative to the south
+    pub latitude: String,
+    /// meters above sea level
+    pub altitude: String,
+}
+#[cfg(feature = "obsolete-types")]
+impl GposRecord {
+    pub fn from_reader(reader: &mut Cursor<&[u8]>) -> Result<Self, std::io::Error> {
+        let mut read_string =
+            || read_character_string(reader).map(|x| String::from_utf8_lossy(&x).into_owned());
+        Ok(GposRecord {
+            longitude: read_string()?,
+            latitude: read_string()?,
+            altitude: read_string()?,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = character_string_bytes(&self.longitude);
+        bytes.extend_from_slice(&character_string_bytes(&self.latitude));
+        bytes.extend_from_slice(&character_string_bytes(&self.altitude));
+        bytes
+    }
+}
+
+/// The OSI network service access point address of the owner name.
+/// See https://datatracker.ietf.org/doc/html/rfc1706#section-5
+#[cfg(feature = "obsolete-types")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct NsapRecord {
+    pub address: Vec<u8>,
+}
+#[cfg(feature = "obsolete-types")]
+impl NsapRecord {
+    /// Reads the rdata starting at the current position of the reader, `data_len`
+    /// bytes long.
+    pub fn from_reader(reader: &mut Cursor<&[u8]>, data_len: u16) -> Result<Self, std::io::Error> {
+        Ok(NsapRecord {
+            address: read_bytes(reader, data_len as usize)?,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.address.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{message, record};
+    use crate::{DNSPacket, DNSRecord, TypeField};
+
+    fn decode(type_field: TypeField, rdata: &[u8]) -> DNSRecord {
+        let data = message(
+            [0, 1, 0, 0],
+            &record("relay.example.com", type_field as u16, rdata),
+        );
+        DNSPacket::from(&data).unwrap().answers.remove(0)
+    }
+
+    #[test]
+    fn decodes_x25() {
+        let record = decode(TypeField::X25, b"\x0c311061700956");
+        assert_eq!(
+            record.x25,
+            Some(X25Record {
+                psdn_address: "311061700956".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn decodes_isdn_with_and_without_subaddress() {
+        let record = decode(TypeField::ISDN, b"\x0f150862028003217\x03004");
+        let isdn = record.isdn.unwrap();
+        assert_eq!(isdn.address, "150862028003217");
+        assert_eq!(isdn.subaddress.as_deref(), Some("004"));
+
+        let record = decode(TypeField::ISDN, b"\x0f150862028003217");
+        assert_eq!(record.isdn.unwrap().subaddress, None);
+    }
+
+    #[test]
+    fn decodes_rt() {
+        let rdata = RtRecord {
+            preference: 10,
+            intermediate_host: DomainName::from("relay.example.com"),
+        }
+        .to_bytes();
+        let record = decode(TypeField::RT, &rdata);
+        let rt = record.rt.unwrap();
+        assert_eq!(rt.preference, 10);
+        assert_eq!(rt.intermediate_host.string, "relay.example.com");
+    }
+
+    const GPOS_RDATA: &[u8] = b"\x08-32.6882\x08116.8652\x0410.0";
+    const NSAP_RDATA: [u8; 20] = [
+        0x47, 0x00, 0x05, 0x80, 0x00, 0x5a, 0x00, 0x00, 0x00, 0x00, 0x01, 0xe1, 0x33, 0xff, 0xff,
+        0xff, 0x00, 0x01, 0x61, 0x00,
+    ];
+
+    #[cfg(feature = "obsolete-types")]
+    #[test]
+    fn decodes_gpos() {
+        let record = decode(TypeField::GPOS, GPOS_RDATA);
+        let gpos = record.gpos.unwrap();
+        assert_eq!(gpos.longitude, "-32.6882");
+        assert_eq!(gpos.latitude, "116.8652");
+        assert_eq!(gpos.altitude, "10.0");
+        assert_eq!(gpos.to_bytes(), GPOS_RDATA);
+    }
+
+    #[cfg(feature = "obsolete-types")]
+    #[test]
+    fn decodes_nsap() {
+        let record = decode(TypeField::NSAP, &NSAP_RDATA);
+        assert_eq!(record.nsap.unwrap().address, NSAP_RDATA);
+    }
+
+    #[cfg(not(feature = "obsolete-types"))]
+    #[test]
+    fn keeps_gpos_and_nsap_as_unknown_rdata() {
+        assert_eq!(decode(TypeField::GPOS, GPOS_RDATA).data, GPOS_RDATA);
+        assert_eq!(decode(TypeField::NSAP, &NSAP_RDATA).data, NSAP_RDATA);
+    }
+}
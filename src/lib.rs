@@ -1,18 +1,87 @@
+use std::collections::HashMap;
 use std::fmt;
 use std::io::{Cursor, Error, ErrorKind, Read};
-use std::net::UdpSocket;
-use std::net::{Ipv4Addr, Ipv6Addr};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use ipv4::ipv4_addr_from_bytes;
 use ipv6::ipv6_addr_from_bytes;
+use reader::{read_character_string, read_u16, read_u32};
 
+pub use afsdb::AfsdbRecord;
+pub use apl::AplRecord;
+pub use cache::Cache;
+pub use cert::CertRecord;
+pub use clock::{Clock, MockClock, SystemClock};
+pub use connection::UdpConnection;
+pub use edns::{EdnsOption, OptRecord, ServerCookies};
+pub use error::{DnsError, ResolutionError, Warning};
+pub use eui::{Eui48Record, Eui64Record, EuiRecord};
+pub use flags::{DNSFlags, OpCode};
+pub use ixfr::ZoneChange;
+pub use kx::KxRecord;
+#[cfg(feature = "obsolete-types")]
+pub use legacy::{GposRecord, NsapRecord};
+pub use legacy::{IsdnRecord, RtRecord, X25Record};
+pub use mx::MxRecord;
+pub use ratelimit::RateLimiter;
+pub use rp::RpRecord;
+pub use rrsig::RrsigRecord;
+pub use selector::{NameserverSelector, ParseOrder};
+pub use soa::SoaRecord;
+pub use srv::SrvRecord;
+pub use sshfp::SshfpRecord;
+pub use svcb::SvcbRecord;
+#[cfg(feature = "unix")]
+pub use system::resolve_system;
+pub use tcp::TcpMessageReader;
+pub use tlsa::TlsaRecord;
+pub use tsig::TsigRecord;
+pub use uri::UriRecord;
+
+pub mod afsdb;
+pub mod apl;
+pub mod cache;
+pub mod cert;
+pub mod clock;
+mod connection;
+mod dump;
+pub mod edns;
+mod error;
+pub mod eui;
+pub mod flags;
 mod ipv4;
 mod ipv6;
+pub mod ixfr;
+pub mod kx;
+pub mod legacy;
+pub mod mx;
+pub mod ratelimit;
+mod reader;
+pub mod rp;
+pub mod rrsig;
+pub mod selector;
+pub mod soa;
+pub mod srv;
+pub mod sshfp;
+pub mod svcb;
+#[cfg(feature = "unix")]
+pub mod system;
+pub mod tcp;
+#[cfg(test)]
+mod testutil;
+pub mod tlsa;
+pub mod tsig;
+pub mod uri;
 
 /// TYPE fields are used in resource records.  Note that these
 /// types are a subset of QTYPEs.
 /// See https://datatracker.ietf.org/doc/html/rfc1035#section-3.2.2
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(u16)]
 pub enum TypeField {
     /// a host address
@@ -47,8 +116,52 @@ pub enum TypeField {
     MX = 15,
     /// text strings
     TXT = 16,
+    /// responsible person
+    RP = 17,
+    /// AFS database location
+    AFSDB = 18,
+    /// X.25 PSDN address
+    X25 = 19,
+    /// ISDN address
+    ISDN = 20,
+    /// route through
+    RT = 21,
+    /// NSAP address
+    NSAP = 22,
+    /// geographical position (Obsolete)
+    GPOS = 27,
     /// aaaa host address
     AAAA = 28,
+    /// the location of a service
+    SRV = 33,
+    /// key exchanger
+    KX = 36,
+    /// a certificate or certificate revocation list
+    CERT = 37,
+    /// EDNS(0) pseudo-record, only found in the additional section
+    OPT = 41,
+    /// lists of address prefixes
+    APL = 42,
+    /// SSH key fingerprint
+    SSHFP = 44,
+    /// the signature of an RRset
+    RRSIG = 46,
+    /// the certificate association of a TLS server, for DANE
+    TLSA = 52,
+    /// general purpose service binding
+    SVCB = 64,
+    /// service binding for HTTP origins
+    HTTPS = 65,
+    /// sender policy framework (Obsolete - use TXT)
+    SPF = 99,
+    /// an EUI-48 address
+    EUI48 = 108,
+    /// an EUI-64 address
+    EUI64 = 109,
+    /// transaction signature, authenticates a single message
+    TSIG = 250,
+    /// a URI
+    URI = 256,
 }
 impl fmt::Display for TypeField {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -69,7 +182,78 @@ impl fmt::Display for TypeField {
             TypeField::MINFO => write!(f, "MINFO"),
             TypeField::MX => write!(f, "MX"),
             TypeField::TXT => write!(f, "TXT"),
+            TypeField::RP => write!(f, "RP"),
+            TypeField::AFSDB => write!(f, "AFSDB"),
+            TypeField::X25 => write!(f, "X25"),
+            TypeField::ISDN => write!(f, "ISDN"),
+            TypeField::RT => write!(f, "RT"),
+            TypeField::NSAP => write!(f, "NSAP"),
+            TypeField::GPOS => write!(f, "GPOS"),
             TypeField::AAAA => write!(f, "AAAA"),
+            TypeField::SRV => write!(f, "SRV"),
+            TypeField::KX => write!(f, "KX"),
+            TypeField::CERT => write!(f, "CERT"),
+            TypeField::OPT => write!(f, "OPT"),
+            TypeField::APL => write!(f, "APL"),
+            TypeField::SSHFP => write!(f, "SSHFP"),
+            TypeField::RRSIG => write!(f, "RRSIG"),
+            TypeField::TLSA => write!(f, "TLSA"),
+            TypeField::SVCB => write!(f, "SVCB"),
+            TypeField::HTTPS => write!(f, "HTTPS"),
+            TypeField::SPF => write!(f, "SPF"),
+            TypeField::EUI48 => write!(f, "EUI48"),
+            TypeField::EUI64 => write!(f, "EUI64"),
+            TypeField::TSIG => write!(f, "TSIG"),
+            TypeField::URI => write!(f, "URI"),
+        }
+    }
+}
+impl FromStr for TypeField {
+    type Err = std::io::Error;
+
+    /// Parses the mnemonic of a type, e.g. `MX`, case-insensitively.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "A" => Ok(TypeField::A),
+            "NS" => Ok(TypeField::NS),
+            "MD" => Ok(TypeField::MD),
+            "MF" => Ok(TypeField::MF),
+            "CNAME" => Ok(TypeField::CNAME),
+            "SOA" => Ok(TypeField::SOA),
+            "MB" => Ok(TypeField::MB),
+            "MG" => Ok(TypeField::MG),
+            "MR" => Ok(TypeField::MR),
+            "NULL" => Ok(TypeField::NULL),
+            "WKS" => Ok(TypeField::WKS),
+            "PTR" => Ok(TypeField::PTR),
+            "HINFO" => Ok(TypeField::HINFO),
+            "MINFO" => Ok(TypeField::MINFO),
+            "MX" => Ok(TypeField::MX),
+            "TXT" => Ok(TypeField::TXT),
+            "RP" => Ok(TypeField::RP),
+            "AFSDB" => Ok(TypeField::AFSDB),
+            "X25" => Ok(TypeField::X25),
+            "ISDN" => Ok(TypeField::ISDN),
+            "RT" => Ok(TypeField::RT),
+            "NSAP" => Ok(TypeField::NSAP),
+            "GPOS" => Ok(TypeField::GPOS),
+            "AAAA" => Ok(TypeField::AAAA),
+            "SRV" => Ok(TypeField::SRV),
+            "KX" => Ok(TypeField::KX),
+            "CERT" => Ok(TypeField::CERT),
+            "OPT" => Ok(TypeField::OPT),
+            "APL" => Ok(TypeField::APL),
+            "SSHFP" => Ok(TypeField::SSHFP),
+            "RRSIG" => Ok(TypeField::RRSIG),
+            "TLSA" => Ok(TypeField::TLSA),
+            "SVCB" => Ok(TypeField::SVCB),
+            "HTTPS" => Ok(TypeField::HTTPS),
+            "SPF" => Ok(TypeField::SPF),
+            "EUI48" => Ok(TypeField::EUI48),
+            "EUI64" => Ok(TypeField::EUI64),
+            "TSIG" => Ok(TypeField::TSIG),
+            "URI" => Ok(TypeField::URI),
+            _ => Err(Error::new(ErrorKind::InvalidInput, "Invalid TYPE field")),
         }
     }
 }
@@ -100,20 +284,112 @@ impl TypeField {
             14 => Ok(TypeField::MINFO),
             15 => Ok(TypeField::MX),
             16 => Ok(TypeField::TXT),
+            17 => Ok(TypeField::RP),
+            18 => Ok(TypeField::AFSDB),
+            19 => Ok(TypeField::X25),
+            20 => Ok(TypeField::ISDN),
+            21 => Ok(TypeField::RT),
+            22 => Ok(TypeField::NSAP),
+            27 => Ok(TypeField::GPOS),
             28 => Ok(TypeField::AAAA),
-            _ => Err(Error::new(ErrorKind::Other, "Invalid TYPE field")),
+            33 => Ok(TypeField::SRV),
+            36 => Ok(TypeField::KX),
+            37 => Ok(TypeField::CERT),
+            41 => Ok(TypeField::OPT),
+            42 => Ok(TypeField::APL),
+            44 => Ok(TypeField::SSHFP),
+            46 => Ok(TypeField::RRSIG),
+            52 => Ok(TypeField::TLSA),
+            64 => Ok(TypeField::SVCB),
+            65 => Ok(TypeField::HTTPS),
+            99 => Ok(TypeField::SPF),
+            108 => Ok(TypeField::EUI48),
+            109 => Ok(TypeField::EUI64),
+            250 => Ok(TypeField::TSIG),
+            256 => Ok(TypeField::URI),
+            _ => Err(Error::other("Invalid TYPE field")),
         }
     }
 
     fn from_reader(reader: &mut Cursor<&[u8]>) -> Result<Self, std::io::Error> {
-        let mut bytes = [0u8; 2];
-        reader.read_exact(&mut bytes)?;
-        TypeField::from_bytes(&bytes)
+        let offset = reader.position();
+        let value = read_u16(reader)?;
+        TypeField::from_bytes(&value.to_be_bytes())
+            .map_err(|_| DnsError::InvalidType { value, offset }.into())
+    }
+}
+
+/// QTYPE fields appear in the question part of a query. They are a superset of
+/// TYPEs, the extra values match more than one type of record.
+/// See https://datatracker.ietf.org/doc/html/rfc1035#section-3.2.3
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QType {
+    Type(TypeField),
+    /// a request for the changes to a zone since a given serial
+    /// See https://datatracker.ietf.org/doc/html/rfc1995
+    IXFR,
+    /// a request for a transfer of an entire zone
+    AXFR,
+    /// a request for mailbox-related records (MB, MG or MR)
+    MAILB,
+    /// a request for mail agent RRs (Obsolete - see MX)
+    MAILA,
+    /// a request for all records
+    ANY,
+}
+impl fmt::Display for QType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            QType::Type(type_field) => write!(f, "{}", type_field),
+            QType::IXFR => write!(f, "IXFR"),
+            QType::AXFR => write!(f, "AXFR"),
+            QType::MAILB => write!(f, "MAILB"),
+            QType::MAILA => write!(f, "MAILA"),
+            QType::ANY => write!(f, "ANY"),
+        }
+    }
+}
+impl From<TypeField> for QType {
+    fn from(type_field: TypeField) -> Self {
+        QType::Type(type_field)
+    }
+}
+impl QType {
+    /// Return the memory representation of this integer as a byte array in big-endian
+    /// (network) byte order.
+    fn to_be_bytes(self) -> [u8; 2] {
+        match self {
+            QType::Type(type_field) => type_field.to_be_bytes(),
+            QType::IXFR => 251u16.to_be_bytes(),
+            QType::AXFR => 252u16.to_be_bytes(),
+            QType::MAILB => 253u16.to_be_bytes(),
+            QType::MAILA => 254u16.to_be_bytes(),
+            QType::ANY => 255u16.to_be_bytes(),
+        }
+    }
+
+    fn from_bytes(data: &[u8]) -> Result<Self, std::io::Error> {
+        let bytes = data.try_into().map_err(|_| ErrorKind::InvalidInput)?;
+        match u16::from_be_bytes(bytes) {
+            251 => Ok(QType::IXFR),
+            252 => Ok(QType::AXFR),
+            253 => Ok(QType::MAILB),
+            254 => Ok(QType::MAILA),
+            255 => Ok(QType::ANY),
+            _ => Ok(QType::Type(TypeField::from_bytes(data)?)),
+        }
+    }
+
+    fn from_reader(reader: &mut Cursor<&[u8]>) -> Result<Self, std::io::Error> {
+        let offset = reader.position();
+        let value = read_u16(reader)?;
+        QType::from_bytes(&value.to_be_bytes())
+            .map_err(|_| DnsError::InvalidType { value, offset }.into())
     }
 }
 
 /// CLASS fields appear in resource records.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u16)]
 pub enum ClassField {
     /// the Internet
@@ -124,6 +400,23 @@ pub enum ClassField {
     CH = 3,
     /// Hesiod [Dyer 87]
     HS = 4,
+    /// no class, dynamic updates use it to delete a single record
+    /// See https://datatracker.ietf.org/doc/html/rfc2136#section-2.4
+    NONE = 254,
+    /// any class, TSIG records and dynamic updates use it
+    ANY = 255,
+}
+impl fmt::Display for ClassField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClassField::IN => write!(f, "IN"),
+            ClassField::CS => write!(f, "CS"),
+            ClassField::CH => write!(f, "CH"),
+            ClassField::HS => write!(f, "HS"),
+            ClassField::NONE => write!(f, "NONE"),
+            ClassField::ANY => write!(f, "ANY"),
+        }
+    }
 }
 impl ClassField {
     /// Return the memory representation of this integer as a byte array in big-endian
@@ -140,14 +433,17 @@ impl ClassField {
             2 => Ok(ClassField::CS),
             3 => Ok(ClassField::CH),
             4 => Ok(ClassField::HS),
-            _ => Err(Error::new(ErrorKind::Other, "Invalid CLASS field")),
+            254 => Ok(ClassField::NONE),
+            255 => Ok(ClassField::ANY),
+            _ => Err(Error::other("Invalid CLASS field")),
         }
     }
 
     fn from_reader(reader: &mut Cursor<&[u8]>) -> Result<Self, std::io::Error> {
-        let mut bytes = [0u8; 2];
-        reader.read_exact(&mut bytes)?;
-        ClassField::from_bytes(&bytes)
+        let offset = reader.position();
+        let value = read_u16(reader)?;
+        ClassField::from_bytes(&value.to_be_bytes())
+            .map_err(|_| DnsError::InvalidClass { value, offset }.into())
     }
 }
 
@@ -156,7 +452,7 @@ const DNS_HEADER_SIZE: usize = 12;
 #[derive(Debug)]
 pub struct DNSHeader {
     pub id: u16,
-    pub flags: u16,
+    pub flags: DNSFlags,
     pub num_questions: u16,
     pub num_answers: u16,
     pub num_authorities: u16,
@@ -166,7 +462,7 @@ impl DNSHeader {
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut bytes = Vec::new();
         bytes.extend_from_slice(&self.id.to_be_bytes());
-        bytes.extend_from_slice(&self.flags.to_be_bytes());
+        bytes.extend_from_slice(&self.flags.bits().to_be_bytes());
         bytes.extend_from_slice(&self.num_questions.to_be_bytes());
         bytes.extend_from_slice(&self.num_answers.to_be_bytes());
         bytes.extend_from_slice(&self.num_authorities.to_be_bytes());
@@ -176,7 +472,7 @@ impl DNSHeader {
 
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, std::io::Error> {
         let id = u16::from_be_bytes(bytes[0..2].try_into().unwrap());
-        let flags = u16::from_be_bytes(bytes[2..4].try_into().unwrap());
+        let flags = DNSFlags::from_bits(u16::from_be_bytes(bytes[2..4].try_into().unwrap()));
         let num_questions = u16::from_be_bytes(bytes[4..6].try_into().unwrap());
         let num_answers = u16::from_be_bytes(bytes[6..8].try_into().unwrap());
         let num_authorities = u16::from_be_bytes(bytes[8..10].try_into().unwrap());
@@ -199,13 +495,29 @@ impl DNSHeader {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct DNSQuestion {
     pub name: DomainName,
-    pub type_field: TypeField,
+    pub type_field: QType,
     pub class: ClassField,
 }
+impl fmt::Display for DNSQuestion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            ";{}.\t\t{}\t{}",
+            self.name.string, self.class, self.type_field
+        )
+    }
+}
 impl DNSQuestion {
+    /// Whether both questions ask for the same thing, names being case-insensitive.
+    pub fn matches(&self, other: &DNSQuestion) -> bool {
+        self.name.string.eq_ignore_ascii_case(&other.name.string)
+            && self.type_field == other.type_field
+            && self.class == other.class
+    }
+
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut bytes = Vec::new();
         bytes.extend_from_slice(&self.name.to_bytes());
@@ -216,7 +528,7 @@ impl DNSQuestion {
 
     pub fn from_reader(reader: &mut Cursor<&[u8]>) -> Result<Self, std::io::Error> {
         let name = DomainName::from_reader(reader)?;
-        let type_field = TypeField::from_reader(reader)?;
+        let type_field = QType::from_reader(reader)?;
         let class = ClassField::from_reader(reader)?;
 
         Ok(DNSQuestion {
@@ -227,64 +539,281 @@ impl DNSQuestion {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct DomainName {
+    /// the name without the trailing dot, bytes that aren't printable ASCII are
+    /// escaped as `\DDD` like `dig` does
     pub string: String,
+    /// the exact bytes of each label, which needn't be ASCII
+    pub raw_labels: Vec<Vec<u8>>,
 }
 
 const MAX_RECURSION_DEPTH: u8 = 5;
 
+/// Limits on the length of names in wire format.
+/// See https://datatracker.ietf.org/doc/html/rfc1035#section-2.3.4
+const MAX_LABEL_LEN: usize = 63;
+const MAX_NAME_LEN: usize = 255;
+
+/// Fails once the labels read so far are longer than a name can be, as compression
+/// pointers could otherwise assemble a longer one. This also caps names at 127
+/// labels, since every label takes at least 2 bytes.
+fn check_wire_len(labels: &[Vec<u8>]) -> Result<(), DnsError> {
+    // every label is preceded by its length, and the name ends with the root
+    let length = labels.iter().map(|x| x.len() + 1).sum::<usize>() + 1;
+    if length > MAX_NAME_LEN {
+        return Err(DnsError::NameTooLong { length });
+    }
+    Ok(())
+}
+
+/// Splits a name in the presentation format into labels, resolving escapes. Invalid
+/// escapes are kept as they are.
+/// See https://datatracker.ietf.org/doc/html/rfc1035#section-5.1
+fn unescape_labels(name: &str) -> Vec<Vec<u8>> {
+    let mut labels = split_labels(name, false).expect("lenient splitting can't fail");
+    labels.retain(|x| !x.is_empty());
+    labels
+}
+
+/// Splits the name on the dots that aren't escaped, keeping empty labels. When
+/// `strict`, fails on a `\DDD` escape that is incomplete or above 255, and on a
+/// backslash at the end of the name, otherwise they are kept as they are.
+fn split_labels(name: &str, strict: bool) -> Result<Vec<Vec<u8>>, DnsError> {
+    let bytes = name.as_bytes();
+    let mut labels = vec![];
+    let mut label = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => {
+                let escaped = match bytes.get(i + 1) {
+                    Some(digit) if digit.is_ascii_digit() => bytes
+                        .get(i + 1..i + 4)
+                        .filter(|x| x.iter().all(u8::is_ascii_digit))
+                        .and_then(|x| std::str::from_utf8(x).ok()?.parse::<u8>().ok())
+                        .map(|byte| (byte, 4)),
+                    Some(byte) => Some((*byte, 2)),
+                    None => None,
+                };
+                match escaped {
+                    Some((byte, length)) => {
+                        label.push(byte);
+                        i += length;
+                    }
+                    None if strict => return Err(DnsError::InvalidEscape { offset: i }),
+                    None => {
+                        label.push(b'\\');
+                        i += 1;
+                    }
+                }
+            }
+            b'.' => {
+                labels.push(std::mem::take(&mut label));
+                i += 1;
+            }
+            byte => {
+                label.push(byte);
+                i += 1;
+            }
+        }
+    }
+    labels.push(label);
+    Ok(labels)
+}
+
+/// Labels are arbitrary bytes, those that would be ambiguous or unprintable in the
+/// presentation format are escaped.
+/// See https://datatracker.ietf.org/doc/html/rfc4343#section-2.1
+fn escape_label(label: &[u8]) -> String {
+    let mut string = String::new();
+    for byte in label {
+        match byte {
+            b'.' | b'\\' => {
+                string.push('\\');
+                string.push(*byte as char);
+            }
+            0x21..=0x7e => string.push(*byte as char),
+            _ => string.push_str(&format!("\\{:03}", byte)),
+        }
+    }
+    string
+}
+
 impl DomainName {
+    /// Both `""` and `"."` are the root, a trailing dot is dropped as names are
+    /// always fully qualified, e.g. `"example.com."` is stored as `"example.com"`.
+    ///
+    /// `\.` is a dot within a label and `\DDD` the byte with decimal value DDD,
+    /// e.g. `foo\.bar.example.com` has the three labels `foo.bar`, `example`, `com`.
+    ///
+    /// Empty labels are dropped, invalid escapes are kept as they are, and lengths
+    /// aren't checked, use `parse` to reject names that can't be encoded.
     pub fn from(domain_name: &str) -> Self {
-        let string = String::from(domain_name);
-        DomainName {
-            string: string.clone(),
+        DomainName::from_labels(unescape_labels(domain_name))
+    }
+
+    /// Like `from`, but fails on empty labels, invalid escapes, labels longer than 63
+    /// bytes, and names longer than 255 bytes once encoded.
+    pub fn parse(domain_name: &str) -> Result<Self, DnsError> {
+        if domain_name.is_empty() || domain_name == "." {
+            return Ok(DomainName::from_labels(vec![]));
+        }
+        let mut labels = split_labels(domain_name, true)?;
+        if labels.last().is_some_and(|x| x.is_empty()) {
+            labels.pop();
+        }
+        if labels.iter().any(|x| x.is_empty()) {
+            return Err(DnsError::EmptyLabel);
+        }
+        if let Some(label) = labels.iter().find(|x| x.len() > MAX_LABEL_LEN) {
+            return Err(DnsError::LabelTooLong {
+                length: label.len(),
+            });
+        }
+        check_wire_len(&labels)?;
+        Ok(DomainName::from_labels(labels))
+    }
+
+    pub fn from_labels(raw_labels: Vec<Vec<u8>>) -> Self {
+        let string = raw_labels
+            .iter()
+            .map(|x| escape_label(x))
+            .collect::<Vec<String>>()
+            .join(".");
+        DomainName { string, raw_labels }
+    }
+
+    /// The name to query PTR records for to find the host name of an IPv4 address,
+    /// e.g. `4.3.2.1.in-addr.arpa` for `1.2.3.4`.
+    pub fn from_ipv4_arpa(addr: Ipv4Addr) -> Self {
+        let [a, b, c, d] = addr.octets();
+        DomainName::from(&format!("{d}.{c}.{b}.{a}.in-addr.arpa"))
+    }
+
+    /// The name to query PTR records for to find the host name of an IPv6 address,
+    /// made of its 32 nibbles in reverse order under `ip6.arpa`.
+    /// See https://datatracker.ietf.org/doc/html/rfc3596#section-2.5
+    pub fn from_ipv6_arpa(addr: Ipv6Addr) -> Self {
+        let mut string = String::new();
+        for byte in addr.octets().iter().rev() {
+            string.push_str(&format!("{:x}.{:x}.", byte & 0x0f, byte >> 4));
         }
+        string.push_str("ip6.arpa");
+        DomainName::from(&string)
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.string.is_empty()
+    }
+
+    /// The name with its trailing dot, as zone files and most tools expect, e.g.
+    /// `example.com.`, or `.` for the root.
+    pub fn to_fqdn(&self) -> String {
+        format!("{}.", self.string)
+    }
+
+    /// The same name with the case of every letter picked at random, the "0x20"
+    /// encoding makes spoofed responses harder to forge as they must match it.
+    /// See https://datatracker.ietf.org/doc/html/draft-vixie-dnsext-dns0x20-00
+    pub fn with_random_case(&self) -> DomainName {
+        let raw_labels = self
+            .raw_labels
+            .iter()
+            .map(|label| {
+                label
+                    .iter()
+                    .map(|x| {
+                        if x.is_ascii_alphabetic() && rand::random::<bool>() {
+                            x ^ 0x20
+                        } else {
+                            *x
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        DomainName::from_labels(raw_labels)
+    }
+
+    /// Whether this name is `zone` or below it, e.g. `www.example.com` is a subdomain
+    /// of `example.com` and of the root. Labels are compared case-insensitively.
+    pub fn is_subdomain_of(&self, zone: &DomainName) -> bool {
+        let labels: Vec<&Vec<u8>> = self.raw_labels.iter().filter(|x| !x.is_empty()).collect();
+        let zone_labels: Vec<&Vec<u8>> = zone.raw_labels.iter().filter(|x| !x.is_empty()).collect();
+        zone_labels.len() <= labels.len()
+            && labels
+                .iter()
+                .rev()
+                .zip(zone_labels.iter().rev())
+                .all(|(x, y)| x.eq_ignore_ascii_case(y))
     }
 
     /// TODO rename as this is not simply converting to bytes, but it's actually
     /// encoding the domain name for DNS questions
+    ///
+    /// Empty labels, as in `"a..b"`, can't be encoded since a zero length marks the
+    /// end of the name, so they are skipped.
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut bytes: Vec<u8> = Vec::new();
-        for part in self.string.split(".") {
-            bytes.push(part.len() as u8);
-            bytes.extend_from_slice(part.as_bytes());
+        for label in self.raw_labels.iter().filter(|x| !x.is_empty()) {
+            bytes.push(label.len() as u8);
+            bytes.extend_from_slice(label);
         }
         bytes.push(0);
         bytes
     }
 
-    fn bytes_from_reader_compressed(
+    /// The wire format with every letter lowercased, the form names are signed in.
+    /// See https://datatracker.ietf.org/doc/html/rfc4034#section-6.2
+    pub fn to_canonical_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.to_bytes();
+        // length bytes are at most 63, so they are never uppercase letters
+        bytes.make_ascii_lowercase();
+        bytes
+    }
+
+    fn labels_from_reader_compressed(
         length: u8,
         reader: &mut Cursor<&[u8]>,
         recursion_depth: u8,
-    ) -> Result<Vec<u8>, std::io::Error> {
+    ) -> Result<Vec<Vec<u8>>, std::io::Error> {
         let mut offset_bytes: [u8; 1] = [0];
         reader.read_exact(&mut offset_bytes)?;
         let pointer_bytes: [u8; 2] = [length & 0b0011_1111, offset_bytes[0]];
         let pointer = u16::from_be_bytes(pointer_bytes);
+        // names only start after the header, and must be inside the message. The
+        // most common target is 12, the question name right after the header, which
+        // answers point to for their owner name
+        if (pointer as usize) < DNS_HEADER_SIZE || (pointer as usize) >= reader.get_ref().len() {
+            return Err(DnsError::BadPointer {
+                pointer,
+                offset: reader.position() - 2,
+            }
+            .into());
+        }
 
         let curr_position = reader.position();
         reader.set_position(pointer as u64);
-        let bytes = DomainName::bytes_from_reader(reader, recursion_depth)?;
+        let labels = DomainName::labels_from_reader(reader, recursion_depth)?;
         reader.set_position(curr_position);
 
-        Ok(bytes)
+        Ok(labels)
     }
 
-    fn bytes_from_reader(
+    fn labels_from_reader(
         reader: &mut Cursor<&[u8]>,
         recursion_depth: u8,
-    ) -> Result<Vec<u8>, std::io::Error> {
+    ) -> Result<Vec<Vec<u8>>, std::io::Error> {
         // A malicious actor could exploit our DNS compression code by sending a DNS
         // response with a DNS compression entry that points to itself, so that read_domain_name would end up in an infinite loop. Fix it to avoid that attack.
         if recursion_depth > MAX_RECURSION_DEPTH {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "Recursion depth exceeded",
-            ));
+            return Err(DnsError::RecursionDepthExceeded {
+                offset: reader.position(),
+            }
+            .into());
         }
-        let mut bytes: Vec<Vec<u8>> = Vec::new();
+        let mut labels: Vec<Vec<u8>> = Vec::new();
         let mut should_read = true;
         while should_read {
             let mut length_bytes: [u8; 1] = [0; 1];
@@ -294,31 +823,32 @@ impl DomainName {
             // https://datatracker.ietf.org/doc/html/rfc1035#section-4.1.4
             let is_compressed = (length & 0b1100_0000) != 0;
             if is_compressed {
-                bytes.push(DomainName::bytes_from_reader_compressed(
+                labels.extend(DomainName::labels_from_reader_compressed(
                     length,
                     reader,
                     recursion_depth + 1,
                 )?);
+                check_wire_len(&labels)?;
                 should_read = false;
             } else if length > 0 {
                 let mut buf = vec![0u8; length as usize];
                 reader.read_exact(&mut buf)?;
-                bytes.push(buf);
+                labels.push(buf);
+                check_wire_len(&labels)?;
             } else {
                 should_read = false;
             }
         }
-        Ok(bytes.join(&u8::from(b'.')))
+        Ok(labels)
     }
 
     pub fn from_reader(reader: &mut Cursor<&[u8]>) -> Result<Self, std::io::Error> {
-        let bytes: Vec<u8> = DomainName::bytes_from_reader(reader, 0)?;
-        let string = String::from_utf8(bytes.clone()).map_err(|_| ErrorKind::InvalidData)?;
-        Ok(DomainName { string })
+        let labels = DomainName::labels_from_reader(reader, 0)?;
+        Ok(DomainName::from_labels(labels))
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct DNSRecord {
     /// the domain name
     pub name: DomainName,
@@ -330,61 +860,625 @@ pub struct DNSRecord {
     pub ttl: u32,
     /// the record’s content, like the IP address.
     data: Vec<u8>,
+    /// the address of an A record, a record holds exactly one
     pub ipv4: Option<Vec<Ipv4Addr>>,
+    /// the address of an AAAA record, a record holds exactly one
     pub ipv6: Option<Vec<Ipv6Addr>>,
     pub ns_name: Option<DomainName>,
     pub cname: Option<DomainName>,
+    pub opt: Option<OptRecord>,
+    pub svcb: Option<SvcbRecord>,
+    pub rrsig: Option<RrsigRecord>,
+    pub soa: Option<SoaRecord>,
+    pub mx: Option<MxRecord>,
+    pub srv: Option<SrvRecord>,
+    pub tsig: Option<TsigRecord>,
+    /// the character-strings of a TXT or SPF record, kept separate as their
+    /// boundaries matter to e.g. DKIM, as raw bytes since they needn't be UTF-8
+    pub txt: Option<Vec<Vec<u8>>>,
+    pub apl: Option<AplRecord>,
+    pub cert: Option<CertRecord>,
+    pub tlsa: Option<TlsaRecord>,
+    pub afsdb: Option<AfsdbRecord>,
+    pub rp: Option<RpRecord>,
+    pub sshfp: Option<SshfpRecord>,
+    pub x25: Option<X25Record>,
+    pub isdn: Option<IsdnRecord>,
+    pub rt: Option<RtRecord>,
+    #[cfg(feature = "obsolete-types")]
+    pub gpos: Option<GposRecord>,
+    #[cfg(feature = "obsolete-types")]
+    pub nsap: Option<NsapRecord>,
+    pub kx: Option<KxRecord>,
+    pub eui48: Option<Eui48Record>,
+    pub eui64: Option<Eui64Record>,
+    pub uri: Option<UriRecord>,
+}
+impl fmt::Display for DNSRecord {
+    /// Formats the record like a zone file entry, as `dig` does.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}.\t{}\t{}\t{}\t",
+            self.name.string, self.ttl, self.class, self.type_field
+        )?;
+        #[cfg(feature = "obsolete-types")]
+        if let Some(gpos) = &self.gpos {
+            write_quoted(f, gpos.longitude.as_bytes())?;
+            write!(f, " ")?;
+            write_quoted(f, gpos.latitude.as_bytes())?;
+            write!(f, " ")?;
+            return write_quoted(f, gpos.altitude.as_bytes());
+        }
+        #[cfg(feature = "obsolete-types")]
+        if let Some(nsap) = &self.nsap {
+            write!(f, "0x")?;
+            return write_hex(f, &nsap.address);
+        }
+        if let Some(ipv4) = &self.ipv4 {
+            write_joined(f, ipv4)
+        } else if let Some(ipv6) = &self.ipv6 {
+            write_joined(f, ipv6)
+        } else if let Some(name) = self.ns_name.as_ref().or(self.cname.as_ref()) {
+            write!(f, "{}.", name.string)
+        } else if let Some(svcb) = &self.svcb {
+            write!(f, "{} {}.", svcb.priority, svcb.target.string)?;
+            for (key, value) in &svcb.params {
+                write!(f, " key{}=", key)?;
+                write_hex(f, value)?;
+            }
+            Ok(())
+        } else if let Some(soa) = &self.soa {
+            write!(
+                f,
+                "{}. {}. {} {} {} {} {}",
+                soa.mname.string,
+                soa.rname.string,
+                soa.serial,
+                soa.refresh,
+                soa.retry,
+                soa.expire,
+                soa.minimum
+            )
+        } else if let Some(mx) = &self.mx {
+            write!(f, "{} {}.", mx.preference, mx.exchange.string)
+        } else if let Some(srv) = &self.srv {
+            write!(
+                f,
+                "{} {} {} {}.",
+                srv.priority, srv.weight, srv.port, srv.target.string
+            )
+        } else if let Some(txt) = &self.txt {
+            for (i, string) in txt.iter().enumerate() {
+                if i > 0 {
+                    write!(f, " ")?;
+                }
+                write_quoted(f, string)?;
+            }
+            Ok(())
+        } else if let Some(apl) = &self.apl {
+            write_joined(f, &apl.0)
+        } else if let Some(tlsa) = &self.tlsa {
+            write!(
+                f,
+                "{} {} {} ",
+                tlsa.usage, tlsa.selector, tlsa.matching_type
+            )?;
+            write_hex(f, &tlsa.cert_association)
+        } else if let Some(afsdb) = &self.afsdb {
+            write!(f, "{} {}.", afsdb.subtype, afsdb.hostname.string)
+        } else if let Some(rp) = &self.rp {
+            write!(f, "{}. {}.", rp.mbox.string, rp.txt.string)
+        } else if let Some(sshfp) = &self.sshfp {
+            write!(f, "{} {} ", sshfp.algorithm, sshfp.fp_type)?;
+            write_hex(f, &sshfp.fingerprint)
+        } else if let Some(x25) = &self.x25 {
+            write_quoted(f, x25.psdn_address.as_bytes())
+        } else if let Some(isdn) = &self.isdn {
+            write_quoted(f, isdn.address.as_bytes())?;
+            if let Some(subaddress) = &isdn.subaddress {
+                write!(f, " ")?;
+                write_quoted(f, subaddress.as_bytes())?;
+            }
+            Ok(())
+        } else if let Some(rt) = &self.rt {
+            write!(f, "{} {}.", rt.preference, rt.intermediate_host.string)
+        } else if let Some(kx) = &self.kx {
+            write!(f, "{} {}.", kx.preference, kx.exchanger.string)
+        } else if let Some(eui48) = &self.eui48 {
+            write!(f, "{}", eui48)
+        } else if let Some(eui64) = &self.eui64 {
+            write!(f, "{}", eui64)
+        } else if let Some(uri) = &self.uri {
+            write!(f, "{} {} ", uri.priority, uri.weight)?;
+            write_quoted(f, uri.target.as_bytes())
+        } else {
+            // unknown rdata format, see https://datatracker.ietf.org/doc/html/rfc3597#section-5
+            write!(f, "\\# {} ", self.data.len())?;
+            write_hex(f, &self.data)
+        }
+    }
+}
+
+fn write_joined<T: fmt::Display>(f: &mut fmt::Formatter, items: &[T]) -> fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            write!(f, " ")?;
+        }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
+
+/// Writes a character-string in quotes, escaping quotes, backslashes and the bytes
+/// that aren't printable.
+fn write_quoted(f: &mut fmt::Formatter, string: &[u8]) -> fmt::Result {
+    write!(f, "\"")?;
+    for &byte in string {
+        match byte {
+            b'"' | b'\\' => write!(f, "\\{}", byte as char)?,
+            0x20..=0x7e => write!(f, "{}", byte as char)?,
+            _ => write!(f, "\\{:03}", byte)?,
+        }
+    }
+    write!(f, "\"")
 }
+
+fn write_hex(f: &mut fmt::Formatter, bytes: &[u8]) -> fmt::Result {
+    for byte in bytes {
+        write!(f, "{:02x}", byte)?;
+    }
+    Ok(())
+}
+
 impl DNSRecord {
+    /// Builds a record from its rdata in wire format, e.g. the 4 bytes of an IPv4
+    /// address for an A record. Names in the rdata must not be compressed.
+    pub fn new(
+        name: DomainName,
+        type_field: TypeField,
+        class: ClassField,
+        ttl: u32,
+        data: &[u8],
+    ) -> Result<DNSRecord, std::io::Error> {
+        let mut bytes = name.to_bytes();
+        bytes.extend_from_slice(&type_field.to_be_bytes());
+        bytes.extend_from_slice(&class.to_be_bytes());
+        bytes.extend_from_slice(&ttl.to_be_bytes());
+        bytes.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(data);
+        DNSRecord::from_reader(&mut Cursor::new(bytes.as_slice()))
+    }
+
+    /// A record with empty rdata, which dynamic updates use to refer to a whole
+    /// RRset: with class ANY to delete it or require that it exists, with class NONE
+    /// to require that it doesn't.
+    /// See https://datatracker.ietf.org/doc/html/rfc2136#section-2.4
+    pub fn without_rdata(name: DomainName, type_field: TypeField, class: ClassField) -> Self {
+        DNSRecord::new(name, type_field, class, 0, &[])
+            .expect("a record without rdata always parses")
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        if let Some(opt) = &self.opt {
+            return opt.to_bytes();
+        }
+        let rdata = self.rdata();
+        let mut bytes = self.name.to_bytes();
+        bytes.extend_from_slice(&self.type_field.to_be_bytes());
+        bytes.extend_from_slice(&self.class.to_be_bytes());
+        bytes.extend_from_slice(&self.ttl.to_be_bytes());
+        bytes.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(&rdata);
+        bytes
+    }
+
+    /// The TTL left once the record has been held since `cached_at`, as it must be
+    /// decremented before the record is handed out again.
+    /// See https://datatracker.ietf.org/doc/html/rfc2181#section-8
+    pub fn ttl_remaining(&self, cached_at: Instant, now: Instant) -> u32 {
+        let held = now.saturating_duration_since(cached_at).as_secs();
+        self.ttl
+            .saturating_sub(u32::try_from(held).unwrap_or(u32::MAX))
+    }
+
+    /// The length of `to_bytes`, i.e. with the names uncompressed: the owner name,
+    /// 10 bytes of type, class, TTL and rdata length, then the rdata.
+    pub fn wire_len(&self) -> usize {
+        if let Some(opt) = &self.opt {
+            return opt.to_bytes().len();
+        }
+        self.name.to_bytes().len() + 10 + self.rdata().len()
+    }
+
+    /// The rdata with the names it contains uncompressed, as compression pointers
+    /// are only meaningful in the packet the record was read from.
+    fn rdata(&self) -> Vec<u8> {
+        #[cfg(feature = "obsolete-types")]
+        if let Some(gpos) = &self.gpos {
+            return gpos.to_bytes();
+        }
+        #[cfg(feature = "obsolete-types")]
+        if let Some(nsap) = &self.nsap {
+            return nsap.to_bytes();
+        }
+        if let Some(name) = self.ns_name.as_ref().or(self.cname.as_ref()) {
+            name.to_bytes()
+        } else if let Some(svcb) = &self.svcb {
+            svcb.to_bytes()
+        } else if let Some(rrsig) = &self.rrsig {
+            rrsig.to_bytes()
+        } else if let Some(soa) = &self.soa {
+            soa.to_bytes()
+        } else if let Some(mx) = &self.mx {
+            mx.to_bytes()
+        } else if let Some(srv) = &self.srv {
+            srv.to_bytes()
+        } else if let Some(tsig) = &self.tsig {
+            tsig.to_bytes()
+        } else if let Some(apl) = &self.apl {
+            apl.to_bytes()
+        } else if let Some(cert) = &self.cert {
+            cert.to_bytes()
+        } else if let Some(tlsa) = &self.tlsa {
+            tlsa.to_bytes()
+        } else if let Some(afsdb) = &self.afsdb {
+            afsdb.to_bytes()
+        } else if let Some(rp) = &self.rp {
+            rp.to_bytes()
+        } else if let Some(sshfp) = &self.sshfp {
+            sshfp.to_bytes()
+        } else if let Some(x25) = &self.x25 {
+            x25.to_bytes()
+        } else if let Some(isdn) = &self.isdn {
+            isdn.to_bytes()
+        } else if let Some(rt) = &self.rt {
+            rt.to_bytes()
+        } else if let Some(kx) = &self.kx {
+            kx.to_bytes()
+        } else if let Some(eui48) = &self.eui48 {
+            eui48.to_bytes()
+        } else if let Some(eui64) = &self.eui64 {
+            eui64.to_bytes()
+        } else if let Some(uri) = &self.uri {
+            uri.to_bytes()
+        } else {
+            self.data.clone()
+        }
+    }
+
     pub fn from_reader(reader: &mut Cursor<&[u8]>) -> Result<DNSRecord, std::io::Error> {
         let name = DomainName::from_reader(reader)?;
         let type_field = TypeField::from_reader(reader)?;
-        let class = ClassField::from_reader(reader)?;
-
-        let mut ttl_bytes = [0u8; 4];
-        reader.read_exact(&mut ttl_bytes)?;
-        let ttl = u32::from_be_bytes(ttl_bytes);
-
-        let mut data_len_bytes = [0u8; 2];
-        reader.read_exact(&mut data_len_bytes)?;
+        let class_offset = reader.position();
+        let class_value = read_u16(reader)?;
+        let class_bytes = class_value.to_be_bytes();
+        // OPT records store the UDP payload size in the CLASS field, see `opt` instead
+        let class = if type_field == TypeField::OPT {
+            ClassField::IN
+        } else {
+            ClassField::from_bytes(&class_bytes).map_err(|_| DnsError::InvalidClass {
+                value: class_value,
+                offset: class_offset,
+            })?
+        };
 
-        let data_len = u16::from_be_bytes(data_len_bytes);
+        let ttl = read_u32(reader)?;
+        let data_len = read_u16(reader)?;
+        let remaining = (reader.get_ref().len() as u64).saturating_sub(reader.position());
+        if u64::from(data_len) > remaining {
+            return Err(DnsError::RdataOverflow {
+                length: data_len,
+                offset: reader.position() - 2,
+            }
+            .into());
+        }
+        // updates leave the rdata empty to refer to a whole RRset
+        let rdata_type = match class {
+            ClassField::ANY | ClassField::NONE if data_len == 0 => None,
+            _ => Some(type_field),
+        };
         let mut data = vec![0u8; data_len as usize];
         let data_position = reader.position();
         reader.read_exact(&mut data)?;
 
-        let ns_name = if type_field == TypeField::NS {
-            reader.set_position(data_position);
-            Some(DomainName::from_reader(reader)?)
-        } else {
-            None
+        let ns_name = match rdata_type {
+            Some(TypeField::NS) => Some(read_rdata(
+                reader,
+                type_field,
+                data_position,
+                data_len,
+                DomainName::from_reader,
+            )?),
+            _ => None,
         };
 
-        let cname = if type_field == TypeField::CNAME {
-            reader.set_position(data_position);
-            Some(DomainName::from_reader(reader)?)
-        } else {
-            None
+        let cname = match rdata_type {
+            Some(TypeField::CNAME) => Some(read_rdata(
+                reader,
+                type_field,
+                data_position,
+                data_len,
+                DomainName::from_reader,
+            )?),
+            _ => None,
         };
 
-        let ipv4: Option<Vec<Ipv4Addr>> = match type_field {
-            TypeField::A => Some(
-                data.chunks(4)
-                    .map(|chunk| ipv4_addr_from_bytes(chunk))
-                    .collect(),
-            ),
+        let ipv4: Option<Vec<Ipv4Addr>> = match rdata_type {
+            Some(TypeField::A) => Some(vec![ipv4_addr_from_bytes(&data)?]),
             _ => None,
         };
 
-        let ipv6: Option<Vec<Ipv6Addr>> = match type_field {
-            TypeField::AAAA => Some(
-                data.chunks(16)
-                    .map(|chunk: &[u8]| ipv6_addr_from_bytes(&chunk))
-                    .collect(),
-            ),
+        let ipv6: Option<Vec<Ipv6Addr>> = match rdata_type {
+            Some(TypeField::AAAA) => Some(vec![ipv6_addr_from_bytes(&data)?]),
+            _ => None,
+        };
+
+        let opt = match rdata_type {
+            Some(TypeField::OPT) => Some(OptRecord::from_parts(
+                u16::from_be_bytes(class_bytes),
+                ttl,
+                &data,
+            )?),
+            _ => None,
+        };
+
+        let svcb = match rdata_type {
+            Some(TypeField::SVCB | TypeField::HTTPS) => Some(read_rdata(
+                reader,
+                type_field,
+                data_position,
+                data_len,
+                |reader| SvcbRecord::from_reader(reader, data_len),
+            )?),
+            _ => None,
+        };
+
+        let rrsig = match rdata_type {
+            Some(TypeField::RRSIG) => Some(read_rdata(
+                reader,
+                type_field,
+                data_position,
+                data_len,
+                |reader| RrsigRecord::from_reader(reader, data_len),
+            )?),
+            _ => None,
+        };
+
+        let soa = match rdata_type {
+            Some(TypeField::SOA) => Some(read_rdata(
+                reader,
+                type_field,
+                data_position,
+                data_len,
+                SoaRecord::from_reader,
+            )?),
+            _ => None,
+        };
+
+        let mx = match rdata_type {
+            Some(TypeField::MX) => Some(read_rdata(
+                reader,
+                type_field,
+                data_position,
+                data_len,
+                MxRecord::from_reader,
+            )?),
+            _ => None,
+        };
+
+        let srv = match rdata_type {
+            Some(TypeField::SRV) => Some(read_rdata(
+                reader,
+                type_field,
+                data_position,
+                data_len,
+                SrvRecord::from_reader,
+            )?),
+            _ => None,
+        };
+
+        let txt = match rdata_type {
+            Some(TypeField::TXT | TypeField::SPF) => {
+                reader.set_position(data_position);
+                let end = data_position + u64::from(data_len);
+                let mut strings = vec![];
+                while reader.position() < end {
+                    let string = read_character_string(reader)?;
+                    strings.push(string);
+                }
+                if reader.position() > end {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        "TXT string overflows rdata",
+                    ));
+                }
+                Some(strings)
+            }
+            _ => None,
+        };
+
+        let apl = match rdata_type {
+            Some(TypeField::APL) => Some(read_rdata(
+                reader,
+                type_field,
+                data_position,
+                data_len,
+                |reader| AplRecord::from_reader(reader, data_len),
+            )?),
+            _ => None,
+        };
+
+        let cert = match rdata_type {
+            Some(TypeField::CERT) => Some(read_rdata(
+                reader,
+                type_field,
+                data_position,
+                data_len,
+                |reader| CertRecord::from_reader(reader, data_len),
+            )?),
+            _ => None,
+        };
+
+        let tlsa = match rdata_type {
+            Some(TypeField::TLSA) => Some(read_rdata(
+                reader,
+                type_field,
+                data_position,
+                data_len,
+                |reader| TlsaRecord::from_reader(reader, data_len),
+            )?),
+            _ => None,
+        };
+
+        let afsdb = match rdata_type {
+            Some(TypeField::AFSDB) => Some(read_rdata(
+                reader,
+                type_field,
+                data_position,
+                data_len,
+                AfsdbRecord::from_reader,
+            )?),
+            _ => None,
+        };
+
+        let rp = match rdata_type {
+            Some(TypeField::RP) => Some(read_rdata(
+                reader,
+                type_field,
+                data_position,
+                data_len,
+                RpRecord::from_reader,
+            )?),
+            _ => None,
+        };
+
+        let sshfp = match rdata_type {
+            Some(TypeField::SSHFP) => Some(read_rdata(
+                reader,
+                type_field,
+                data_position,
+                data_len,
+                |reader| SshfpRecord::from_reader(reader, data_len),
+            )?),
             _ => None,
         };
 
+        let x25 = match rdata_type {
+            Some(TypeField::X25) => Some(read_rdata(
+                reader,
+                type_field,
+                data_position,
+                data_len,
+                X25Record::from_reader,
+            )?),
+            _ => None,
+        };
+
+        let isdn = match rdata_type {
+            Some(TypeField::ISDN) => Some(read_rdata(
+                reader,
+                type_field,
+                data_position,
+                data_len,
+                |reader| IsdnRecord::from_reader(reader, data_len),
+            )?),
+            _ => None,
+        };
+
+        let rt = match rdata_type {
+            Some(TypeField::RT) => Some(read_rdata(
+                reader,
+                type_field,
+                data_position,
+                data_len,
+                RtRecord::from_reader,
+            )?),
+            _ => None,
+        };
+
+        #[cfg(feature = "obsolete-types")]
+        let gpos = match rdata_type {
+            Some(TypeField::GPOS) => Some(read_rdata(
+                reader,
+                type_field,
+                data_position,
+                data_len,
+                GposRecord::from_reader,
+            )?),
+            _ => None,
+        };
+
+        #[cfg(feature = "obsolete-types")]
+        let nsap = match rdata_type {
+            Some(TypeField::NSAP) => Some(read_rdata(
+                reader,
+                type_field,
+                data_position,
+                data_len,
+                |reader| NsapRecord::from_reader(reader, data_len),
+            )?),
+            _ => None,
+        };
+
+        let kx = match rdata_type {
+            Some(TypeField::KX) => Some(read_rdata(
+                reader,
+                type_field,
+                data_position,
+                data_len,
+                KxRecord::from_reader,
+            )?),
+            _ => None,
+        };
+
+        let eui48 = match rdata_type {
+            Some(TypeField::EUI48) => Some(read_rdata(
+                reader,
+                type_field,
+                data_position,
+                data_len,
+                |reader| Eui48Record::from_reader(reader, data_len),
+            )?),
+            _ => None,
+        };
+
+        let eui64 = match rdata_type {
+            Some(TypeField::EUI64) => Some(read_rdata(
+                reader,
+                type_field,
+                data_position,
+                data_len,
+                |reader| Eui64Record::from_reader(reader, data_len),
+            )?),
+            _ => None,
+        };
+
+        let uri = match rdata_type {
+            Some(TypeField::URI) => Some(read_rdata(
+                reader,
+                type_field,
+                data_position,
+                data_len,
+                |reader| UriRecord::from_reader(reader, data_len),
+            )?),
+            _ => None,
+        };
+
+        let tsig = match rdata_type {
+            Some(TypeField::TSIG) => Some(read_rdata(
+                reader,
+                type_field,
+                data_position,
+                data_len,
+                TsigRecord::from_reader,
+            )?),
+            _ => None,
+        };
+
+        // names in the rdata may be compressed, always continue right after it
+        reader.set_position(data_position + u64::from(data_len));
+
         Ok(DNSRecord {
             name,
             type_field,
@@ -395,10 +1489,120 @@ impl DNSRecord {
             ipv6,
             ns_name,
             cname,
+            opt,
+            svcb,
+            rrsig,
+            soa,
+            mx,
+            srv,
+            tsig,
+            txt,
+            apl,
+            cert,
+            tlsa,
+            afsdb,
+            rp,
+            sshfp,
+            x25,
+            isdn,
+            rt,
+            #[cfg(feature = "obsolete-types")]
+            gpos,
+            #[cfg(feature = "obsolete-types")]
+            nsap,
+            kx,
+            eui48,
+            eui64,
+            uri,
         })
     }
 }
 
+/// Parses the rdata of `data_len` bytes at `data_position` with `parse`, which must
+/// read all of it and nothing more, names in the rdata can still point elsewhere.
+fn read_rdata<T>(
+    reader: &mut Cursor<&[u8]>,
+    type_field: TypeField,
+    data_position: u64,
+    data_len: u16,
+    parse: impl FnOnce(&mut Cursor<&[u8]>) -> Result<T, std::io::Error>,
+) -> Result<T, std::io::Error> {
+    reader.set_position(data_position);
+    let rdata = parse(reader)?;
+    if reader.position() != data_position + u64::from(data_len) {
+        return Err(DnsError::RdataLength {
+            type_field,
+            length: data_len,
+            offset: data_position,
+        }
+        .into());
+    }
+    Ok(rdata)
+}
+
+/// Records the warning for a question or record that can be skipped because of a
+/// TYPE or CLASS we don't know about. Other errors are returned.
+fn warn_skipped(error: std::io::Error, warnings: &mut Vec<Warning>) -> Result<(), std::io::Error> {
+    match DnsError::from_io(&error).cloned() {
+        Some(DnsError::InvalidType { value, offset }) => {
+            warnings.push(Warning::UnknownType { value, offset });
+            Ok(())
+        }
+        Some(DnsError::InvalidClass { value, offset }) => {
+            warnings.push(Warning::UnknownClass { value, offset });
+            Ok(())
+        }
+        _ => Err(error),
+    }
+}
+
+/// Returns the offset of the TYPE of the question or record starting at `start`,
+/// right after its name. The error may come from its rdata, e.g. a type covered by
+/// an RRSIG, so the offset it carries can't be used.
+fn type_offset(reader: &mut Cursor<&[u8]>, start: u64) -> Result<u64, std::io::Error> {
+    reader.set_position(start);
+    DomainName::from_reader(reader)?;
+    Ok(reader.position())
+}
+
+fn records_from_reader_lenient(
+    reader: &mut Cursor<&[u8]>,
+    count: u16,
+    warnings: &mut Vec<Warning>,
+) -> Result<Vec<DNSRecord>, std::io::Error> {
+    let mut records = vec![];
+    for _ in 0..count {
+        let start = reader.position();
+        match DNSRecord::from_reader(reader) {
+            Ok(record) => records.push(record),
+            Err(error) => {
+                warn_skipped(error, warnings)?;
+                // skip the TYPE, CLASS and TTL, then the rdata
+                let type_offset = type_offset(reader, start)?;
+                reader.set_position(type_offset + 8);
+                let data_len = read_u16(reader)?;
+                reader.set_position(reader.position() + u64::from(data_len));
+            }
+        }
+    }
+    Ok(records)
+}
+
+/// What a response tells about the question it answers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseKind {
+    /// the answer section has records of the type asked for
+    Answer,
+    /// the server isn't authoritative and points to the name servers of a subzone
+    Referral,
+    /// the name exists but has no records of the type asked for
+    NoData,
+    /// the name doesn't exist
+    NXDomain,
+    /// the name is an alias, but the records of its target aren't included
+    CnameChain,
+}
+
 #[derive(Debug)]
 pub struct DNSPacket {
     pub header: DNSHeader,
@@ -407,18 +1611,157 @@ pub struct DNSPacket {
     pub authorities: Vec<DNSRecord>,
     pub additionals: Vec<DNSRecord>,
 }
-impl DNSPacket {
-    pub fn from(data: &[u8]) -> Result<Self, std::io::Error> {
-        let mut reader = Cursor::new(data);
-
-        let header = DNSHeader::from_reader(&mut reader)?;
-
-        let mut questions = vec![];
-        for _ in 0..header.num_questions {
-            questions.push(DNSQuestion::from_reader(&mut reader)?);
+impl fmt::Display for DNSPacket {
+    /// Formats the packet section by section, as `dig` does.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            ";; opcode: {}, status: {}, id: {}",
+            self.header.flags.opcode(),
+            self.header.flags.rcode(),
+            self.header.id
+        )?;
+        if let Some(opt) = self.get_opt() {
+            writeln!(f, "\n;; OPT PSEUDOSECTION:")?;
+            writeln!(
+                f,
+                "; EDNS: version: {}, udp: {}",
+                opt.version, opt.udp_payload_size
+            )?;
+            if let Some(nsid) = opt.nsid() {
+                write!(f, "; NSID: ")?;
+                write_hex(f, &nsid)?;
+                writeln!(f, " (\"{}\")", String::from_utf8_lossy(&nsid))?;
+            }
         }
-
-        let mut answers = vec![];
+        writeln!(f, "\n;; QUESTION SECTION:")?;
+        for question in &self.questions {
+            writeln!(f, "{}", question)?;
+        }
+        let sections = [
+            ("ANSWER", &self.answers),
+            ("AUTHORITY", &self.authorities),
+            ("ADDITIONAL", &self.additionals),
+        ];
+        for (title, records) in sections {
+            let records: Vec<&DNSRecord> = records.iter().filter(|x| x.opt.is_none()).collect();
+            if records.is_empty() {
+                continue;
+            }
+            writeln!(f, "\n;; {} SECTION:", title)?;
+            for record in records {
+                writeln!(f, "{}", record)?;
+            }
+        }
+        Ok(())
+    }
+}
+impl DNSPacket {
+    /// Builds a packet from its sections, the counts in the header are set from
+    /// the length of each section.
+    pub fn new(
+        mut header: DNSHeader,
+        questions: Vec<DNSQuestion>,
+        answers: Vec<DNSRecord>,
+        authorities: Vec<DNSRecord>,
+        additionals: Vec<DNSRecord>,
+    ) -> Self {
+        header.num_questions = questions.len() as u16;
+        header.num_answers = answers.len() as u16;
+        header.num_authorities = authorities.len() as u16;
+        header.num_additionals = additionals.len() as u16;
+        DNSPacket {
+            header,
+            questions,
+            answers,
+            authorities,
+            additionals,
+        }
+    }
+
+    /// Builds the response to `query`, echoing its id and questions. The opcode and
+    /// RD bit are copied from the query as well.
+    pub fn response_to(query: &DNSPacket, answers: Vec<DNSRecord>) -> DNSPacket {
+        let header = DNSHeader {
+            id: query.header.id,
+            flags: DNSFlags::default()
+                .with_response(true)
+                .with_opcode(query.header.flags.opcode())
+                .with_recursion_desired(query.header.flags.recursion_desired()),
+            num_questions: 0,
+            num_answers: 0,
+            num_authorities: 0,
+            num_additionals: 0,
+        };
+        DNSPacket::new(header, query.questions.clone(), answers, vec![], vec![])
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.header.to_bytes();
+        for question in &self.questions {
+            bytes.extend_from_slice(&question.to_bytes());
+        }
+        for record in self
+            .answers
+            .iter()
+            .chain(&self.authorities)
+            .chain(&self.additionals)
+        {
+            bytes.extend_from_slice(&record.to_bytes());
+        }
+        bytes
+    }
+
+    /// Rebuilds the query this packet answers from its id and questions, e.g. to
+    /// send it again over TCP after a truncated response. The opcode and RD bit are
+    /// kept, the other flags and the records are dropped.
+    pub fn to_query_bytes(&self) -> Vec<u8> {
+        let header = DNSHeader {
+            id: self.header.id,
+            flags: DNSFlags::default()
+                .with_opcode(self.header.flags.opcode())
+                .with_recursion_desired(self.header.flags.recursion_desired()),
+            num_questions: 0,
+            num_answers: 0,
+            num_authorities: 0,
+            num_additionals: 0,
+        };
+        DNSPacket::new(header, self.questions.clone(), vec![], vec![], vec![]).to_bytes()
+    }
+
+    pub fn from(data: &[u8]) -> Result<Self, std::io::Error> {
+        DNSPacket::from_prefix(data).map(|(packet, _)| packet)
+    }
+
+    /// Reads the header and the first question, and ignores the rest of the message,
+    /// which is all a server needs to answer most queries.
+    pub fn parse_question_only(data: &[u8]) -> Result<(DNSHeader, DNSQuestion), std::io::Error> {
+        let mut reader = Cursor::new(data);
+        let header = DNSHeader::from_reader(&mut reader)?;
+        if header.num_questions == 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "Message has no question",
+            ));
+        }
+        let question = DNSQuestion::from_reader(&mut reader)?;
+        Ok((header, question))
+    }
+
+    /// Parses the packet at the start of `data`, e.g. a buffer holding several
+    /// messages read from a TCP stream, and returns it with the number of bytes it
+    /// took. Compression pointers are offsets from the start of `data`.
+    pub fn from_prefix(data: &[u8]) -> Result<(Self, usize), std::io::Error> {
+        let mut reader = Cursor::new(data);
+
+        let header = DNSHeader::from_reader(&mut reader)?;
+
+        let mut questions = vec![];
+        for _ in 0..header.num_questions {
+            questions.push(DNSQuestion::from_reader(&mut reader)?);
+        }
+
+        let mut answers = vec![];
         for _ in 0..header.num_answers {
             answers.push(DNSRecord::from_reader(&mut reader)?);
         }
@@ -433,17 +1776,140 @@ impl DNSPacket {
             additionals.push(DNSRecord::from_reader(&mut reader)?);
         }
 
-        Ok(DNSPacket {
+        let packet = DNSPacket {
             header,
             questions,
             answers,
             authorities,
             additionals,
-        })
+        };
+        Ok((packet, reader.position() as usize))
+    }
+
+    /// Like `from`, but skips the questions and records of unknown TYPE or CLASS
+    /// instead of failing, and reports them along with other oddities as warnings.
+    /// The header counts are updated to match what was kept.
+    pub fn from_lenient(data: &[u8]) -> Result<(Self, Vec<Warning>), std::io::Error> {
+        let mut reader = Cursor::new(data);
+        let mut warnings = vec![];
+
+        let mut header = DNSHeader::from_reader(&mut reader)?;
+        if header.flags.has_reserved_bit() {
+            warnings.push(Warning::ReservedFlag {
+                z: header.flags.z(),
+            });
+        }
+
+        let mut questions = vec![];
+        for _ in 0..header.num_questions {
+            let start = reader.position();
+            match DNSQuestion::from_reader(&mut reader) {
+                Ok(question) => questions.push(question),
+                Err(error) => {
+                    warn_skipped(error, &mut warnings)?;
+                    // the TYPE and CLASS are the end of a question
+                    let type_offset = type_offset(&mut reader, start)?;
+                    reader.set_position(type_offset + 4);
+                }
+            }
+        }
+
+        let answers = records_from_reader_lenient(&mut reader, header.num_answers, &mut warnings)?;
+        let authorities =
+            records_from_reader_lenient(&mut reader, header.num_authorities, &mut warnings)?;
+        let additionals =
+            records_from_reader_lenient(&mut reader, header.num_additionals, &mut warnings)?;
+
+        let end = reader.position() as usize;
+        if end > data.len() {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                "Skipped record overflows the packet",
+            ));
+        }
+        if end < data.len() {
+            warnings.push(Warning::TrailingBytes {
+                count: data.len() - end,
+            });
+        }
+
+        header.num_questions = questions.len() as u16;
+        header.num_answers = answers.len() as u16;
+        header.num_authorities = authorities.len() as u16;
+        header.num_additionals = additionals.len() as u16;
+        let packet = DNSPacket {
+            header,
+            questions,
+            answers,
+            authorities,
+            additionals,
+        };
+        Ok((packet, warnings))
+    }
+
+    /// Shows the raw bytes of a packet next to how they were parsed, section by
+    /// section, stopping at the first parse error.
+    pub fn debug_dump(data: &[u8]) -> String {
+        dump::debug_dump(data)
+    }
+
+    /// Checks that the packet goes in the expected direction, that the header counts
+    /// match the sections, and that its opcode is one we support.
+    pub fn validate(&self, expect_query: bool) -> Result<(), std::io::Error> {
+        if self.header.flags.is_response() == expect_query {
+            let message = if expect_query {
+                "Expected a query but got a response"
+            } else {
+                "Expected a response but got a query"
+            };
+            return Err(Error::new(ErrorKind::InvalidData, message));
+        }
+        let counts = [
+            (self.header.num_questions, self.questions.len()),
+            (self.header.num_answers, self.answers.len()),
+            (self.header.num_authorities, self.authorities.len()),
+            (self.header.num_additionals, self.additionals.len()),
+        ];
+        if counts.iter().any(|(count, len)| *count as usize != *len) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "Header counts don't match the sections",
+            ));
+        }
+        match self.header.flags.opcode() {
+            OpCode::Query | OpCode::IQuery | OpCode::Notify | OpCode::Update => Ok(()),
+            _ => Err(Error::new(ErrorKind::Unsupported, "Unsupported opcode")),
+        }
+    }
+
+    /// Secondary servers are told about zone changes with NOTIFY messages.
+    pub fn is_notify(&self) -> bool {
+        self.header.flags.opcode() == OpCode::Notify
+    }
+
+    /// A truncated response didn't fit in a UDP datagram, the query should be
+    /// retried over TCP to get all of it.
+    pub fn is_truncated(&self) -> bool {
+        self.header.flags.is_truncated()
     }
 
+    /// A packet made of just the header, e.g. an empty NOTIFY acknowledgement.
+    pub fn is_empty(&self) -> bool {
+        self.questions.is_empty()
+            && self.answers.is_empty()
+            && self.authorities.is_empty()
+            && self.additionals.is_empty()
+    }
+
+    /// The first A record owned by the queried name, or by the end of its CNAME chain.
     pub fn get_answer(&self) -> Option<&DNSRecord> {
-        self.answers.iter().find(|x| x.type_field == TypeField::A)
+        let Some(question) = self.questions.first() else {
+            return self.answers.iter().find(|x| x.type_field == TypeField::A);
+        };
+        let name = self.cname_target(&question.name);
+        self.answers.iter().find(|x| {
+            x.type_field == TypeField::A && x.name.string.eq_ignore_ascii_case(&name.string)
+        })
     }
 
     pub fn get_nameserver_record(&self) -> Option<&DNSRecord> {
@@ -452,32 +1918,372 @@ impl DNSPacket {
             .find(|x| x.type_field == TypeField::A)
     }
 
+    /// The address of the name server `ns` given in the additional section.
+    pub fn glue_for(&self, ns: &DomainName) -> Option<Ipv4Addr> {
+        self.additionals
+            .iter()
+            .filter(|x| {
+                x.type_field == TypeField::A && x.name.string.eq_ignore_ascii_case(&ns.string)
+            })
+            .find_map(|x| x.ipv4.as_ref().and_then(|x| x.first()))
+            .copied()
+    }
+
+    /// The IPv6 address of the name server `ns` given in the additional section.
+    pub fn glue_v6_for(&self, ns: &DomainName) -> Option<Ipv6Addr> {
+        self.additionals
+            .iter()
+            .filter(|x| {
+                x.type_field == TypeField::AAAA && x.name.string.eq_ignore_ascii_case(&ns.string)
+            })
+            .find_map(|x| x.ipv6.as_ref().and_then(|x| x.first()))
+            .copied()
+    }
+
     pub fn get_nameserver(&self) -> Option<&DNSRecord> {
         self.authorities
             .iter()
             .find(|x| x.type_field == TypeField::NS)
     }
 
+    /// The targets of all the NS records in the authority section, in order.
+    pub fn nameserver_names(&self) -> Vec<DomainName> {
+        self.authorities
+            .iter()
+            .filter(|x| x.type_field == TypeField::NS)
+            .filter_map(|x| x.ns_name.clone())
+            .collect()
+    }
+
     pub fn get_cname(&self) -> Option<&DNSRecord> {
         self.answers
             .iter()
             .find(|x| x.type_field == TypeField::CNAME)
     }
+
+    /// The addresses of the A and AAAA answers, in order, ready to connect to.
+    pub fn socket_addrs(&self, port: u16) -> Vec<SocketAddr> {
+        let mut addrs = vec![];
+        for answer in &self.answers {
+            if let Some(ipv4) = &answer.ipv4 {
+                addrs.extend(ipv4.iter().map(|x| SocketAddr::new(IpAddr::V4(*x), port)));
+            }
+            if let Some(ipv6) = &answer.ipv6 {
+                addrs.extend(ipv6.iter().map(|x| SocketAddr::new(IpAddr::V6(*x), port)));
+            }
+        }
+        addrs
+    }
+
+    /// The mail exchange to try first: the one with the lowest preference, picking
+    /// randomly among equally preferred ones.
+    /// See https://datatracker.ietf.org/doc/html/rfc5321#section-5.1
+    pub fn preferred_mx(&self) -> Option<&DomainName> {
+        let exchanges: Vec<&MxRecord> = self.answers.iter().filter_map(|x| x.mx.as_ref()).collect();
+        let lowest = exchanges.iter().map(|x| x.preference).min()?;
+        let preferred: Vec<&DomainName> = exchanges
+            .into_iter()
+            .filter(|x| x.preference == lowest)
+            .map(|x| &x.exchange)
+            .collect();
+        Some(preferred[rand::random::<usize>() % preferred.len()])
+    }
+
+    /// Classifies the response to its first question.
+    pub fn classify(&self) -> ResponseKind {
+        if self.header.flags.rcode() == flags::RCODE_NXDOMAIN {
+            return ResponseKind::NXDomain;
+        }
+        let question = self.questions.first();
+        let is_answer = |record: &DNSRecord| match question.map(|x| x.type_field) {
+            Some(QType::Type(type_field)) => record.type_field == type_field,
+            _ => true,
+        };
+        if self.answers.iter().any(is_answer) {
+            return ResponseKind::Answer;
+        }
+        if let Some(question) = question {
+            let target = self.cname_target(&question.name);
+            if !target.string.eq_ignore_ascii_case(&question.name.string) {
+                return ResponseKind::CnameChain;
+            }
+        }
+        if self.get_nameserver().is_some() && self.negative_soa().is_none() {
+            ResponseKind::Referral
+        } else {
+            ResponseKind::NoData
+        }
+    }
+
+    /// Removes the answers that are exact duplicates of an earlier one, keeping the
+    /// order of the others.
+    pub fn dedup_answers(&mut self) {
+        let mut answers: Vec<DNSRecord> = Vec::with_capacity(self.answers.len());
+        for answer in self.answers.drain(..) {
+            if !answers.contains(&answer) {
+                answers.push(answer);
+            }
+        }
+        self.answers = answers;
+        self.header.num_answers = self.answers.len() as u16;
+    }
+
+    /// How many records of each type there are across the answer, authority and
+    /// additional sections, OPT included.
+    pub fn type_histogram(&self) -> HashMap<TypeField, usize> {
+        let mut histogram = HashMap::new();
+        for record in self
+            .answers
+            .iter()
+            .chain(&self.authorities)
+            .chain(&self.additionals)
+        {
+            *histogram.entry(record.type_field).or_insert(0) += 1;
+        }
+        histogram
+    }
+
+    /// The effective TTL of the answers when cached as a whole.
+    pub fn min_answer_ttl(&self) -> Option<u32> {
+        self.answers.iter().map(|x| x.ttl).min()
+    }
+
+    /// The SOA that NXDOMAIN and NODATA responses carry in their authority section,
+    /// its `minimum` tells how long the negative answer can be cached.
+    pub fn negative_soa(&self) -> Option<&SoaRecord> {
+        self.authorities.iter().find_map(|x| x.soa.as_ref())
+    }
+
+    /// Whether the server answered with authority for `name`: the AA bit only
+    /// applies to the name in the question, not e.g. to the targets of a CNAME.
+    /// See https://datatracker.ietf.org/doc/html/rfc1035#section-4.1.1
+    pub fn is_authoritative_for(&self, name: &DomainName) -> bool {
+        self.header.flags.is_authoritative()
+            && self
+                .questions
+                .first()
+                .is_some_and(|x| x.name.string.eq_ignore_ascii_case(&name.string))
+    }
+
+    /// A server that was delegated the zone but answers without authority for it:
+    /// no answers, not authoritative, and neither a referral nor a negative answer.
+    pub fn is_lame_delegation(&self) -> bool {
+        self.answers.is_empty()
+            && !self.header.flags.is_authoritative()
+            && self.get_nameserver().is_none()
+            && self.negative_soa().is_none()
+    }
+
+    pub fn get_opt(&self) -> Option<&OptRecord> {
+        self.additionals.iter().find_map(|x| x.opt.as_ref())
+    }
+
+    /// The largest UDP message the sender can receive: the payload size of its OPT
+    /// record, or 512 bytes without EDNS.
+    /// See https://datatracker.ietf.org/doc/html/rfc6891#section-6.2.3
+    pub fn max_udp_size(&self) -> usize {
+        match self.get_opt() {
+            Some(opt) => usize::from(opt.udp_payload_size).max(MIN_UDP_PAYLOAD_SIZE),
+            None => MIN_UDP_PAYLOAD_SIZE,
+        }
+    }
+
+    /// The EDNS version the server answered with, if it speaks EDNS at all.
+    pub fn edns_version(&self) -> Option<u8> {
+        self.get_opt().map(|x| x.version)
+    }
+
+    /// The 12-bit RCODE, whose upper 8 bits are stored in the OPT record.
+    pub fn extended_rcode(&self) -> u16 {
+        let upper = self.get_opt().map_or(0, |x| u16::from(x.extended_rcode));
+        (upper << 4) | u16::from(self.header.flags.rcode())
+    }
+
+    /// Follows the CNAME chain in the answer section starting from `name`, and
+    /// returns the name at the end of it, `name` itself if it isn't an alias.
+    pub fn cname_target<'a>(&'a self, name: &'a DomainName) -> &'a DomainName {
+        let mut name = name;
+        // every hop consumes a CNAME record, so a longer chain must contain a loop
+        for _ in 0..self.answers.len() {
+            match self
+                .answers
+                .iter()
+                .find(|x| {
+                    x.type_field == TypeField::CNAME
+                        && x.name.string.eq_ignore_ascii_case(&name.string)
+                })
+                .and_then(|x| x.cname.as_ref())
+            {
+                Some(target) => name = target,
+                None => break,
+            }
+        }
+        name
+    }
+
+    /// Follows the CNAME chain in the answer section, starting from the queried name,
+    /// and returns the addresses of the A records found at the end of it.
+    pub fn resolved_addresses(&self) -> Vec<Ipv4Addr> {
+        let name = match self.questions.first() {
+            Some(question) => &question.name,
+            None => match self.answers.first() {
+                Some(answer) => &answer.name,
+                None => return vec![],
+            },
+        };
+        let target = self.cname_target(name);
+        self.answers
+            .iter()
+            .filter(|x| {
+                x.type_field == TypeField::A && x.name.string.eq_ignore_ascii_case(&target.string)
+            })
+            .filter_map(|x| x.ipv4.as_ref())
+            .flatten()
+            .copied()
+            .collect()
+    }
+}
+
+/// The port name servers listen on.
+pub const DEFAULT_DNS_PORT: u16 = 53;
+
+/// The UDP payload size advertised in our OPT records, as recommended by
+/// https://www.dnsflagday.net/2020/
+const UDP_PAYLOAD_SIZE: u16 = 1232;
+
+/// Every host must accept UDP messages of this size, the limit without EDNS.
+/// See https://datatracker.ietf.org/doc/html/rfc1035#section-2.3.4
+const MIN_UDP_PAYLOAD_SIZE: usize = 512;
+
+/// Checks that `query` fits the UDP payload size it advertises, or 512 bytes without
+/// EDNS. A larger query, e.g. with many EDNS options or questions, must be sent
+/// over TCP instead.
+pub fn check_udp_size(query: &[u8]) -> Result<(), std::io::Error> {
+    let max_size = DNSPacket::from(query)?.max_udp_size();
+    if query.len() > max_size {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "Query of {} bytes is larger than the {} bytes UDP allows, use TCP",
+                query.len(),
+                max_size
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// How long to wait for a server to respond before giving up on it
+pub const QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Extra settings for `build_query_with_options`, the defaults build the same
+/// query as `build_query`.
+#[derive(Debug, Clone, Default)]
+pub struct QueryOptions {
+    /// Asks the server to resolve the name on our behalf
+    pub recursion_desired: bool,
+    /// Sends an OPT record with this client cookie, see https://datatracker.ietf.org/doc/html/rfc7873
+    pub client_cookie: Option<[u8; edns::CLIENT_COOKIE_LEN]>,
+    /// Sent after the client cookie, the server cookie the server returned last
+    pub server_cookie: Option<Vec<u8>>,
+    /// Sets the DO bit of the OPT record, asking for RRSIG, DS, DNSKEY... records
+    pub dnssec_ok: bool,
+    /// Pads the query with the EDNS padding option to a multiple of this many bytes,
+    /// so its length doesn't reveal the name, see https://datatracker.ietf.org/doc/html/rfc8467
+    pub padding_block_size: Option<u16>,
+    /// Randomizes the case of the letters of the name, a response must echo it
+    /// exactly, see `validate_question_case`
+    pub randomize_case: bool,
+    /// Asks the server for its identifier, see `OptRecord::nsid`
+    pub request_nsid: bool,
+    /// Sets the CD bit, asking a validating resolver for the data even if its
+    /// DNSSEC validation fails
+    pub checking_disabled: bool,
 }
 
+/// Builds a query with the RD bit off, so the server answers from its own data and
+/// refers us to other servers instead of resolving the name on our behalf.
 pub fn build_query(domain_name: &DomainName, type_field: TypeField) -> Vec<u8> {
+    build_query_with_options(domain_name, type_field, &QueryOptions::default())
+}
+
+pub fn build_query_with_options(
+    domain_name: &DomainName,
+    type_field: TypeField,
+    options: &QueryOptions,
+) -> Vec<u8> {
+    let opt = build_opt(options);
+
     let id = rand::random::<u16>();
     let header = DNSHeader {
         id,
-        flags: 0,
+        flags: DNSFlags::default()
+            .with_recursion_desired(options.recursion_desired)
+            .with_checking_disabled(options.checking_disabled),
+        num_questions: 1,
+        num_answers: 0,
+        num_authorities: 0,
+        num_additionals: opt.is_some() as u16,
+    };
+    let name = if options.randomize_case {
+        domain_name.with_random_case()
+    } else {
+        domain_name.clone()
+    };
+    let question = DNSQuestion {
+        name,
+        type_field: type_field.into(),
+        class: ClassField::IN,
+    };
+    let mut bytes = header.to_bytes();
+    bytes.extend_from_slice(&question.to_bytes());
+    if let Some(mut opt) = opt {
+        if let Some(block_size) = options.padding_block_size.filter(|x| *x > 0) {
+            let block_size = block_size as usize;
+            // the padding option itself takes 4 bytes before its data
+            let unpadded_len = bytes.len() + opt.to_bytes().len() + 4;
+            opt = opt.with_padding((block_size - unpadded_len % block_size) % block_size);
+        }
+        bytes.extend_from_slice(&opt.to_bytes());
+    }
+    bytes
+}
+
+/// The OPT record is only sent when one of the EDNS options is requested.
+fn build_opt(options: &QueryOptions) -> Option<OptRecord> {
+    if options.client_cookie.is_none()
+        && !options.dnssec_ok
+        && options.padding_block_size.is_none()
+        && !options.request_nsid
+    {
+        return None;
+    }
+    let mut opt = OptRecord::new(UDP_PAYLOAD_SIZE).with_dnssec_ok(options.dnssec_ok);
+    if let Some(cookie) = options.client_cookie {
+        opt = opt.with_cookies(cookie, options.server_cookie.as_deref().unwrap_or_default());
+    }
+    if options.request_nsid {
+        opt = opt.with_nsid_request();
+    }
+    Some(opt)
+}
+
+/// Builds a NOTIFY message telling a secondary server that `zone` has changed.
+/// See https://datatracker.ietf.org/doc/html/rfc1996#section-3
+pub fn build_notify(zone: &DomainName) -> Vec<u8> {
+    let header = DNSHeader {
+        id: rand::random::<u16>(),
+        flags: DNSFlags::default()
+            .with_opcode(OpCode::Notify)
+            .with_authoritative(true),
         num_questions: 1,
         num_answers: 0,
         num_authorities: 0,
         num_additionals: 0,
     };
     let question = DNSQuestion {
-        name: domain_name.clone(),
-        type_field,
+        name: zone.clone(),
+        type_field: TypeField::SOA.into(),
         class: ClassField::IN,
     };
     let mut bytes = header.to_bytes();
@@ -485,58 +2291,2705 @@ pub fn build_query(domain_name: &DomainName, type_field: TypeField) -> Vec<u8> {
     bytes
 }
 
-fn send_query(socket_address: Ipv4Addr, socket_buf: &[u8]) -> Result<DNSPacket, std::io::Error> {
-    let socket = UdpSocket::bind("0.0.0.0:34254").expect("couldn't bind to address");
-    socket.connect(socket_address.to_string() + ":53")?;
-    socket.send(socket_buf)?;
+/// Builds a dynamic update of `zone`, applied only if all of `prerequisites` hold.
+/// `updates` are added with their own class, deleted with class NONE (a single
+/// record) or ANY and no rdata (a whole RRset, see `DNSRecord::without_rdata`).
+/// The zone is the question, the prerequisites and updates take the place of the
+/// answer and authority sections.
+/// See https://datatracker.ietf.org/doc/html/rfc2136#section-2
+pub fn build_update(
+    zone: &DomainName,
+    prerequisites: &[DNSRecord],
+    updates: &[DNSRecord],
+) -> Vec<u8> {
+    let header = DNSHeader {
+        id: rand::random::<u16>(),
+        flags: DNSFlags::default().with_opcode(OpCode::Update),
+        num_questions: 1,
+        num_answers: prerequisites.len() as u16,
+        num_authorities: updates.len() as u16,
+        num_additionals: 0,
+    };
+    let question = DNSQuestion {
+        name: zone.clone(),
+        type_field: TypeField::SOA.into(),
+        class: ClassField::IN,
+    };
+    let mut bytes = header.to_bytes();
+    bytes.extend_from_slice(&question.to_bytes());
+    for record in prerequisites.iter().chain(updates) {
+        bytes.extend_from_slice(&record.to_bytes());
+    }
+    bytes
+}
+
+/// Builds an inverse query asking for the names that own `record`, e.g. an A record
+/// with the address to look up. It has no question, only `record` as its answer.
+/// See https://datatracker.ietf.org/doc/html/rfc1035#section-6.4
+pub fn build_iquery(record: &DNSRecord) -> Vec<u8> {
+    let header = DNSHeader {
+        id: rand::random::<u16>(),
+        flags: DNSFlags::default().with_opcode(OpCode::IQuery),
+        num_questions: 0,
+        num_answers: 1,
+        num_authorities: 0,
+        num_additionals: 0,
+    };
+    let mut bytes = header.to_bytes();
+    bytes.extend_from_slice(&record.to_bytes());
+    bytes
+}
+
+/// Servers that don't implement EDNS answer without an OPT record, or with one but
+/// without a cookie. Those that returned a cookie before must always echo ours.
+fn validate_cookie(
+    packet: &DNSPacket,
+    client_cookie: &[u8; edns::CLIENT_COOKIE_LEN],
+    name_server: IpAddr,
+    server_cookies: &ServerCookies,
+) -> Result<(), std::io::Error> {
+    let required = server_cookies.get(name_server).is_some();
+    match packet.get_opt() {
+        Some(opt) => {
+            opt.validate_cookie(client_cookie, required)?;
+            if let Some(server_cookie) = opt.server_cookie() {
+                server_cookies.insert(name_server, server_cookie);
+            }
+            Ok(())
+        }
+        None if required => Err(Error::new(
+            ErrorKind::InvalidData,
+            "Response is missing the cookie",
+        )),
+        None => Ok(()),
+    }
+}
 
-    let mut buf = [0; 1024];
-    let (_amt, _src) = socket.recv_from(&mut buf)?;
+/// Checks that the response answers the questions of the query, a response to
+/// another query that happens to have the same id could otherwise be accepted.
+pub fn validate_question(query: &DNSPacket, response: &DNSPacket) -> Result<(), std::io::Error> {
+    let matches = query.questions.len() == response.questions.len()
+        && query
+            .questions
+            .iter()
+            .zip(&response.questions)
+            .all(|(x, y)| x.matches(y));
+    if !matches {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "Response question doesn't match the query",
+        ));
+    }
+    Ok(())
+}
 
-    DNSPacket::from(&buf)
+/// Servers copy the question into the response byte for byte, so a response whose
+/// name differs in case from the query, e.g. built with `randomize_case`, is spoofed.
+pub fn validate_question_case(
+    query: &DNSPacket,
+    response: &DNSPacket,
+) -> Result<(), std::io::Error> {
+    let names = |packet: &DNSPacket| -> Vec<Vec<Vec<u8>>> {
+        packet
+            .questions
+            .iter()
+            .map(|x| x.name.raw_labels.clone())
+            .collect()
+    };
+    if names(query) != names(response) {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "Response question doesn't match the case of the query",
+        ));
+    }
+    Ok(())
 }
 
-pub fn resolve(
+/// We only speak EDNS version 0, responses from servers that don't support it or
+/// that answer with another version can't be interpreted.
+fn validate_edns_version(packet: &DNSPacket) -> Result<(), std::io::Error> {
+    if packet.extended_rcode() == edns::BADVERS {
+        return Err(Error::new(
+            ErrorKind::Unsupported,
+            "Server doesn't support our EDNS version",
+        ));
+    }
+    match packet.edns_version() {
+        Some(version) if version != edns::EDNS_VERSION => Err(Error::new(
+            ErrorKind::Unsupported,
+            format!("Unexpected EDNS version {} in response", version),
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn send_query(
+    source_address: Option<IpAddr>,
+    socket_address: IpAddr,
+    port: u16,
+    timeout: Duration,
+    socket_buf: &[u8],
+) -> Result<DNSPacket, std::io::Error> {
+    let connection = match source_address {
+        Some(source_address) => {
+            UdpConnection::connect_from(source_address, socket_address, port, timeout)?
+        }
+        None => UdpConnection::connect(socket_address, port, timeout)?,
+    };
+    connection.query(socket_buf)
+}
+
+/// Asks `name_server` to resolve the name recursively on our behalf, e.g. a public
+/// resolver like `1.1.1.1`, and returns its whole response. Unlike the queries of
+/// the iterative resolution, this one has the RD bit on.
+pub fn resolve_with_server(
     domain_name: &DomainName,
     type_field: TypeField,
-) -> Result<Ipv4Addr, std::io::Error> {
-    // K-root
-    let mut name_server = Ipv4Addr::new(193, 0, 14, 129);
-    loop {
-        log::info!(
-            "Querying {} for {} {}",
-            name_server,
-            type_field,
-            domain_name.string
-        );
-        let query = build_query(domain_name, type_field);
-        let packet = send_query(name_server, query.as_slice())?;
-        if let Some(answer) = packet
-            .get_answer()
-            .and_then(|x| x.ipv4.as_ref().and_then(|x| x.first()))
-        {
-            return Ok(*answer);
-        } else if let Some(cname_domain) = packet.get_cname().and_then(|x| x.cname.as_ref()) {
-            return resolve(cname_domain, TypeField::A);
-        } else if let Some(name_server_ip) = packet
-            .get_nameserver_record()
-            .and_then(|x| x.ipv4.as_ref().and_then(|x| x.first()))
-        {
-            name_server = *name_server_ip;
-            continue;
-        } else if let Some(ns_domain) = packet.get_nameserver().and_then(|x| x.ns_name.as_ref()) {
-            name_server = resolve(ns_domain, TypeField::A)?;
-            continue;
-        } else {
-            log::error!(
-                "No answer found for {} at {}",
-                domain_name.string,
-                name_server
-            );
-            return Err(Error::new(
-                ErrorKind::Other,
-                "No answer found for domain name",
-            ));
-        }
+    name_server: Ipv4Addr,
+) -> Result<DNSPacket, std::io::Error> {
+    resolve_with_server_on_port(domain_name, type_field, name_server, DEFAULT_DNS_PORT)
+}
+
+/// Like `resolve_with_server`, for resolvers listening on a non-standard port.
+pub fn resolve_with_server_on_port(
+    domain_name: &DomainName,
+    type_field: TypeField,
+    name_server: Ipv4Addr,
+    port: u16,
+) -> Result<DNSPacket, std::io::Error> {
+    UdpConnection::connect(IpAddr::V4(name_server), port, QUERY_TIMEOUT)?
+        .resolve(domain_name, type_field)
+}
+
+/// The span wrapping one step of the resolution, a query sent to `name_server`.
+#[cfg(feature = "tracing")]
+fn query_span(
+    query: &[u8],
+    name_server: IpAddr,
+    domain_name: &DomainName,
+    type_field: TypeField,
+) -> tracing::Span {
+    tracing::info_span!(
+        "query",
+        id = u16::from_be_bytes([query[0], query[1]]),
+        server = %name_server,
+        name = %domain_name.string,
+        type_field = %type_field,
+    )
+}
+
+/// K-root, where iterative resolution starts by default
+const ROOT_HINT: Ipv4Addr = Ipv4Addr::new(193, 0, 14, 129);
+
+/// Which address `Resolver::resolve` returns when the answer has more than one.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum AddressSelection {
+    /// always the first one, in the order the server sent them
+    #[default]
+    First,
+    /// the next one on every call, to spread the load across all of them
+    RoundRobin,
+    /// a random one on every call
+    Random,
+}
+
+/// Settings of the iterative resolution.
+#[derive(Debug, Clone)]
+pub struct ResolverConfig {
+    /// Query all the name servers of a delegation at once and use the first valid
+    /// response, instead of trying them one after the other
+    pub parallel_queries: bool,
+    /// Waits between queries to the same name server, can be shared between
+    /// resolutions so that they all respect it
+    pub rate_limiter: Option<Arc<RateLimiter>>,
+    /// The port name servers are queried on
+    pub port: u16,
+    /// How long to wait for each name server to respond
+    pub timeout: Duration,
+    /// The root name servers resolution starts from
+    pub root_hints: Vec<Ipv4Addr>,
+    /// Ignore glue for name servers outside the zone of the server that sent the
+    /// referral, as it could be used to poison the resolution
+    pub in_bailiwick_glue_only: bool,
+    /// Which address `Resolver::resolve` picks among those in the answer
+    pub address_selection: AddressSelection,
+    /// How long a whole resolution can take, however many name servers it asks
+    pub total_timeout: Option<Duration>,
+    /// Randomize the case of the names in queries and reject responses that don't
+    /// echo it, see `QueryOptions::randomize_case`
+    pub randomize_case: bool,
+    /// The order in which the name servers of a delegation are queried
+    pub nameserver_selector: Arc<dyn NameserverSelector>,
+    /// Ignore answers owned by a name other than the queried one or the target of
+    /// its CNAME chain, e.g. glue put in the answer section by mistake
+    pub answer_name_must_match: bool,
+    /// Set to true to abort the resolutions using this config before their next
+    /// query, they fail with `ErrorKind::Interrupted`
+    pub cancellation: Option<Arc<AtomicBool>>,
+    /// How many times a query is sent over UDP before retrying it over TCP when no
+    /// response arrives in time, e.g. on lossy networks. `None` sends it once and
+    /// never falls back to TCP
+    pub udp_attempts_before_tcp: Option<u32>,
+    /// Query name servers over IPv6 first when a referral has glue of both families,
+    /// over IPv4 first otherwise. The other family is tried when they all fail
+    pub prefer_ipv6: bool,
+    /// The local address queries are sent from, over UDP and TCP, any of them if
+    /// `None`. It must be of the family of the name servers, see `prefer_ipv6`
+    pub source_address: Option<IpAddr>,
+    /// The servers that returned a cookie, whose responses must then always have one
+    pub server_cookies: Arc<ServerCookies>,
+}
+impl Default for ResolverConfig {
+    fn default() -> Self {
+        ResolverConfig {
+            parallel_queries: false,
+            rate_limiter: None,
+            port: DEFAULT_DNS_PORT,
+            timeout: QUERY_TIMEOUT,
+            root_hints: vec![ROOT_HINT],
+            in_bailiwick_glue_only: true,
+            address_selection: AddressSelection::First,
+            total_timeout: None,
+            randomize_case: false,
+            nameserver_selector: Arc::new(ParseOrder),
+            answer_name_must_match: true,
+            cancellation: None,
+            udp_attempts_before_tcp: None,
+            prefer_ipv6: false,
+            source_address: None,
+            server_cookies: Arc::default(),
+        }
+    }
+}
+
+/// Resolves names iteratively from the root according to its config, answering
+/// from a cache if one was given. Its queries have the RD bit off, as it follows
+/// the referrals itself, see `resolve_with_server` to use a recursive resolver.
+#[derive(Debug, Clone, Default)]
+pub struct Resolver {
+    config: ResolverConfig,
+    cache: Option<Arc<Cache>>,
+    /// how many addresses were picked so far, for `AddressSelection::RoundRobin`
+    selections: Arc<AtomicUsize>,
+}
+impl Resolver {
+    pub fn new(config: ResolverConfig) -> Self {
+        Resolver {
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// Shares `cache` with the other resolvers it was given to.
+    pub fn with_cache(mut self, cache: Arc<Cache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub fn config(&self) -> &ResolverConfig {
+        &self.config
+    }
+
+    /// Returns one of the IPv4 addresses among the answers, which one depends on
+    /// the `address_selection` of the config.
+    pub fn resolve(
+        &self,
+        domain_name: &DomainName,
+        type_field: TypeField,
+    ) -> Result<Ipv4Addr, std::io::Error> {
+        let addresses: Vec<Ipv4Addr> = self
+            .resolve_records(domain_name, type_field)?
+            .iter()
+            .filter_map(|x| x.ipv4.as_ref())
+            .flatten()
+            .copied()
+            .collect();
+        if addresses.is_empty() {
+            return Err(Error::other("No answer found for domain name"));
+        }
+        let index = match self.config.address_selection {
+            AddressSelection::First => 0,
+            AddressSelection::RoundRobin => {
+                self.selections.fetch_add(1, Ordering::Relaxed) % addresses.len()
+            }
+            AddressSelection::Random => rand::random::<usize>() % addresses.len(),
+        };
+        Ok(addresses[index])
+    }
+
+    /// Resolves every name on its own thread, like `resolve`, and returns the results
+    /// in the order of `names`.
+    pub fn resolve_many(
+        &self,
+        names: &[(DomainName, TypeField)],
+    ) -> Vec<Result<Ipv4Addr, std::io::Error>> {
+        thread::scope(|scope| {
+            let handles: Vec<_> = names
+                .iter()
+                .map(|(domain_name, type_field)| {
+                    scope.spawn(move || self.resolve(domain_name, *type_field))
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|x| x.join().expect("resolution thread panicked"))
+                .collect()
+        })
+    }
+
+    /// Returns all the records of type `type_field` in the answer section of the
+    /// authoritative response.
+    pub fn resolve_records(
+        &self,
+        domain_name: &DomainName,
+        type_field: TypeField,
+    ) -> Result<Vec<DNSRecord>, std::io::Error> {
+        match &self.cache {
+            Some(cache) => cache.resolve_with(domain_name, type_field, |name, type_field| {
+                resolve_records_with_config(name, type_field, &self.config)
+            }),
+            None => resolve_records_with_config(domain_name, type_field, &self.config),
+        }
+    }
+
+    /// Like `resolve`, bypassing the cache, but also returns every response received
+    /// on the way, like `dig +trace` does.
+    pub fn resolve_trace(
+        &self,
+        domain_name: &DomainName,
+        type_field: TypeField,
+    ) -> Result<(Ipv4Addr, Vec<TraceStep>), std::io::Error> {
+        let deadline = self.config.total_timeout.map(|x| Instant::now() + x);
+        let mut trace = vec![];
+        let address = resolve_records_from_root(
+            domain_name,
+            type_field,
+            &self.config,
+            deadline,
+            Depth::default(),
+            Some(&mut trace),
+        )
+        .map_err(|error| with_delegation_path(error, &trace))?
+        .iter()
+        .find_map(|x| x.ipv4.as_ref().and_then(|x| x.first()))
+        .copied()
+        .ok_or_else(|| Error::other("No answer found for domain name"))?;
+        Ok((address, trace))
+    }
+}
+
+#[deprecated(note = "use `Resolver::resolve` instead")]
+pub fn resolve(
+    domain_name: &DomainName,
+    type_field: TypeField,
+) -> Result<Ipv4Addr, std::io::Error> {
+    resolve_with_config(domain_name, type_field, &ResolverConfig::default())
+}
+
+pub fn resolve_with_config(
+    domain_name: &DomainName,
+    type_field: TypeField,
+    config: &ResolverConfig,
+) -> Result<Ipv4Addr, std::io::Error> {
+    resolve_records_with_config(domain_name, type_field, config)?
+        .iter()
+        .find_map(|x| x.ipv4.as_ref().and_then(|x| x.first()))
+        .copied()
+        .ok_or_else(|| Error::other("No answer found for domain name"))
+}
+
+/// Follows delegations from the root and returns all the records of type `type_field`
+/// in the answer section of the authoritative response.
+pub fn resolve_records(
+    domain_name: &DomainName,
+    type_field: TypeField,
+) -> Result<Vec<DNSRecord>, std::io::Error> {
+    resolve_records_with_config(domain_name, type_field, &ResolverConfig::default())
+}
+
+pub fn resolve_records_with_config(
+    domain_name: &DomainName,
+    type_field: TypeField,
+    config: &ResolverConfig,
+) -> Result<Vec<DNSRecord>, std::io::Error> {
+    let deadline = config.total_timeout.map(|x| Instant::now() + x);
+    let mut trace = vec![];
+    resolve_records_from_root(
+        domain_name,
+        type_field,
+        config,
+        deadline,
+        Depth::default(),
+        Some(&mut trace),
+    )
+    .map_err(|error| with_delegation_path(error, &trace))
+}
+
+/// Tells which name servers responded before `error` ended the resolution.
+fn with_delegation_path(error: std::io::Error, trace: &[TraceStep]) -> std::io::Error {
+    ResolutionError {
+        error,
+        path: trace.iter().map(|x| x.server).collect(),
+    }
+    .into()
+}
+
+/// A response received while following delegations, see `Resolver::resolve_trace`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceStep {
+    /// the name server that answered
+    pub server: IpAddr,
+    pub domain_name: DomainName,
+    pub type_field: TypeField,
+    pub kind: ResponseKind,
+    pub answers: Vec<DNSRecord>,
+    /// the NS records of a referral, or the SOA of a negative answer
+    pub authorities: Vec<DNSRecord>,
+}
+impl fmt::Display for TraceStep {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            ";; {:?} from {} for {} {}",
+            self.kind, self.server, self.domain_name.string, self.type_field
+        )?;
+        for record in self.answers.iter().chain(&self.authorities) {
+            writeln!(f, "{}", record)?;
+        }
+        Ok(())
+    }
+}
+
+/// How long to wait for the next response, `timeout` unless less time is left
+/// before `deadline`. Fails once the deadline has passed.
+fn time_left(deadline: Option<Instant>, timeout: Duration) -> Result<Duration, std::io::Error> {
+    let Some(deadline) = deadline else {
+        return Ok(timeout);
+    };
+    match deadline.checked_duration_since(Instant::now()) {
+        Some(left) if !left.is_zero() => Ok(left.min(timeout)),
+        _ => Err(Error::new(
+            ErrorKind::TimedOut,
+            "Resolution took longer than its total timeout",
+        )),
+    }
+}
+
+fn is_timeout(error: &std::io::Error) -> bool {
+    // depending on the platform a read timeout is reported as either
+    matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut)
+}
+
+/// Sends the query over UDP, then over TCP once the UDP attempts of the config
+/// have all timed out. Queries too large for UDP go straight to TCP.
+fn send_query_with_fallback(
+    name_server: IpAddr,
+    config: &ResolverConfig,
+    deadline: Option<Instant>,
+    query: &[u8],
+) -> Result<DNSPacket, std::io::Error> {
+    let udp_attempts = if let Err(error) = check_udp_size(query) {
+        log::info!("{}", error);
+        0
+    } else {
+        config.udp_attempts_before_tcp.unwrap_or(1)
+    };
+    for attempt in 1..=udp_attempts {
+        let timeout = time_left(deadline, config.timeout)?;
+        match send_query(
+            config.source_address,
+            name_server,
+            config.port,
+            timeout,
+            query,
+        ) {
+            Err(error) if is_timeout(&error) => {
+                log::warn!(
+                    "UDP attempt {}/{} to {} timed out",
+                    attempt,
+                    udp_attempts,
+                    name_server
+                );
+                if config.udp_attempts_before_tcp.is_none() {
+                    return Err(error);
+                }
+            }
+            result => return result,
+        }
+    }
+    log::info!("Falling back to TCP for {}", name_server);
+    tcp::query(
+        config.source_address,
+        name_server,
+        config.port,
+        time_left(deadline, config.timeout)?,
+        query,
+    )
+}
+
+/// Fails once the resolution has been cancelled through the config.
+fn check_cancelled(config: &ResolverConfig) -> Result<(), std::io::Error> {
+    match &config.cancellation {
+        Some(cancelled) if cancelled.load(Ordering::Relaxed) => Err(Error::new(
+            ErrorKind::Interrupted,
+            "Resolution was cancelled",
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// How many times resolution can restart at the target of a CNAME, to break loops
+const MAX_CNAME_DEPTH: u8 = 8;
+/// How many referrals a resolution can follow, there are rarely more than 3
+const MAX_REFERRALS: u8 = 16;
+/// How many name server names without glue can be resolved within one another,
+/// e.g. when the name servers of a zone are only named within it
+const MAX_NAMESERVER_DEPTH: u8 = 4;
+
+/// How far a resolution has gone down CNAMEs and name servers without glue.
+#[derive(Debug, Clone, Copy, Default)]
+struct Depth {
+    cname: u8,
+    nameserver: u8,
+}
+
+fn resolve_records_from_root(
+    domain_name: &DomainName,
+    type_field: TypeField,
+    config: &ResolverConfig,
+    deadline: Option<Instant>,
+    depth: Depth,
+    mut trace: Option<&mut Vec<TraceStep>>,
+) -> Result<Vec<DNSRecord>, std::io::Error> {
+    let mut name_servers: Vec<IpAddr> = config.root_hints.iter().copied().map(IpAddr::V4).collect();
+    // the zone the servers we are querying are authoritative for
+    let mut bailiwick = DomainName::from(".");
+    let mut referrals = 0;
+    loop {
+        time_left(deadline, config.timeout)?;
+        let (server, packet) =
+            query_name_servers(&name_servers, domain_name, type_field, config, deadline)?;
+        let kind = packet.classify();
+        if let Some(trace) = trace.as_deref_mut() {
+            trace.push(TraceStep {
+                server,
+                domain_name: domain_name.clone(),
+                type_field,
+                kind,
+                answers: packet.answers.clone(),
+                authorities: packet.authorities.clone(),
+            });
+        }
+        match kind {
+            ResponseKind::Answer => {
+                let owner = packet.cname_target(domain_name);
+                let answers = packet
+                    .answers
+                    .iter()
+                    .filter(|x| x.type_field == type_field)
+                    .filter(|x| {
+                        let matches = x.name.string.eq_ignore_ascii_case(&owner.string);
+                        if !matches && config.answer_name_must_match {
+                            log::warn!(
+                                "Ignoring answer for {} instead of {}",
+                                x.name.string,
+                                owner.string
+                            );
+                        }
+                        matches || !config.answer_name_must_match
+                    })
+                    .cloned()
+                    .collect();
+                return Ok(answers);
+            }
+            ResponseKind::CnameChain => {
+                // the answer is an alias without the records it points to, start over
+                if depth.cname >= MAX_CNAME_DEPTH {
+                    return Err(DnsError::CnameChainTooLong {
+                        name: domain_name.string.clone(),
+                    }
+                    .into());
+                }
+                let cname_target = packet.cname_target(domain_name);
+                return resolve_records_from_root(
+                    cname_target,
+                    type_field,
+                    config,
+                    deadline,
+                    Depth {
+                        cname: depth.cname + 1,
+                        ..depth
+                    },
+                    trace,
+                );
+            }
+            ResponseKind::Referral => {
+                referrals += 1;
+                if referrals > MAX_REFERRALS {
+                    return Err(DnsError::DelegationTooDeep {
+                        name: domain_name.string.clone(),
+                    }
+                    .into());
+                }
+                let mut glue_v4 = vec![];
+                let mut glue_v6 = vec![];
+                for ns_name in packet.nameserver_names() {
+                    let v4 = packet.glue_for(&ns_name).map(IpAddr::V4);
+                    let v6 = packet.glue_v6_for(&ns_name).map(IpAddr::V6);
+                    if v4.is_none() && v6.is_none() {
+                        continue;
+                    }
+                    if config.in_bailiwick_glue_only && !ns_name.is_subdomain_of(&bailiwick) {
+                        log::warn!(
+                            "Ignoring glue for {} outside of {}",
+                            ns_name.string,
+                            bailiwick.string
+                        );
+                        continue;
+                    }
+                    glue_v4.extend(v4);
+                    glue_v6.extend(v6);
+                }
+                let glue = if config.prefer_ipv6 {
+                    [glue_v6, glue_v4].concat()
+                } else {
+                    [glue_v4, glue_v6].concat()
+                };
+                name_servers = if glue.is_empty() {
+                    if depth.nameserver >= MAX_NAMESERVER_DEPTH {
+                        return Err(DnsError::DelegationTooDeep {
+                            name: domain_name.string.clone(),
+                        }
+                        .into());
+                    }
+                    resolve_nameserver_names(
+                        &packet.nameserver_names(),
+                        config,
+                        deadline,
+                        depth.nameserver + 1,
+                        trace.as_deref_mut(),
+                    )?
+                } else {
+                    glue
+                };
+                // a referral always has an NS record
+                bailiwick = packet.get_nameserver().unwrap().name.clone();
+            }
+            ResponseKind::NXDomain => {
+                return Err(DnsError::NXDomain {
+                    name: domain_name.string.clone(),
+                }
+                .into());
+            }
+            ResponseKind::NoData => {
+                return Err(DnsError::NoData {
+                    name: domain_name.string.clone(),
+                    type_field,
+                }
+                .into());
+            }
+        }
+    }
+}
+
+/// Resolves the addresses of the name servers of a referral without glue, trying each
+/// name in turn until one of them has an address.
+fn resolve_nameserver_names(
+    ns_names: &[DomainName],
+    config: &ResolverConfig,
+    deadline: Option<Instant>,
+    nameserver_depth: u8,
+    mut trace: Option<&mut Vec<TraceStep>>,
+) -> Result<Vec<IpAddr>, std::io::Error> {
+    let mut last_error = None;
+    for ns_name in ns_names {
+        check_cancelled(config)?;
+        match resolve_records_from_root(
+            ns_name,
+            TypeField::A,
+            config,
+            deadline,
+            Depth {
+                cname: 0,
+                nameserver: nameserver_depth,
+            },
+            trace.as_deref_mut(),
+        ) {
+            Ok(records) => {
+                let addresses: Vec<IpAddr> = records
+                    .iter()
+                    .filter_map(|x| x.ipv4.as_ref())
+                    .flatten()
+                    .copied()
+                    .map(IpAddr::V4)
+                    .collect();
+                if !addresses.is_empty() {
+                    return Ok(addresses);
+                }
+            }
+            Err(e) => {
+                log::warn!("Could not resolve name server {}: {}", ns_name.string, e);
+                last_error = Some(e);
+            }
+        }
+    }
+    match last_error {
+        Some(e) => Err(e),
+        None => Ok(vec![]),
+    }
+}
+
+/// Asks the name servers of a delegation step, in order or all at once depending on
+/// the config, and returns the first valid response along with the server it came from.
+fn query_name_servers(
+    name_servers: &[IpAddr],
+    domain_name: &DomainName,
+    type_field: TypeField,
+    config: &ResolverConfig,
+    deadline: Option<Instant>,
+) -> Result<(IpAddr, DNSPacket), std::io::Error> {
+    let name_servers = config.nameserver_selector.order(name_servers.to_vec());
+    if config.parallel_queries && name_servers.len() > 1 {
+        let (sender, receiver) = mpsc::channel();
+        for name_server in name_servers.iter().copied() {
+            let sender = sender.clone();
+            let domain_name = domain_name.clone();
+            let config = config.clone();
+            thread::spawn(move || {
+                let result =
+                    query_name_server(name_server, &domain_name, type_field, &config, deadline)
+                        .map(|packet| (name_server, packet));
+                // the receiver is gone once another server has answered
+                let _ = sender.send(result);
+            });
+        }
+        drop(sender);
+        for result in receiver {
+            match result {
+                Ok(response) => return Ok(response),
+                Err(error) => log::warn!("Query for {} failed: {}", domain_name.string, error),
+            }
+        }
+    } else {
+        for name_server in name_servers.iter().copied() {
+            match query_name_server(name_server, domain_name, type_field, config, deadline) {
+                Ok(packet) => return Ok((name_server, packet)),
+                Err(error) if error.kind() == ErrorKind::Interrupted => return Err(error),
+                Err(error) => log::warn!("Query to {} failed: {}", name_server, error),
+            }
+        }
+    }
+    // running out of time or being cancelled isn't the fault of the name servers
+    check_cancelled(config)?;
+    time_left(deadline, config.timeout)?;
+    Err(DnsError::Unreachable { name_servers }.into())
+}
+
+fn query_name_server(
+    name_server: IpAddr,
+    domain_name: &DomainName,
+    type_field: TypeField,
+    config: &ResolverConfig,
+    deadline: Option<Instant>,
+) -> Result<DNSPacket, std::io::Error> {
+    check_cancelled(config)?;
+    if let Some(rate_limiter) = &config.rate_limiter {
+        rate_limiter.wait(name_server);
+    }
+    log::info!(
+        "Querying {} for {} {}",
+        name_server,
+        type_field,
+        domain_name.string
+    );
+    let client_cookie = config.server_cookies.client_cookie(name_server);
+    let options = QueryOptions {
+        // we follow the referrals ourselves, RD=1 would ask the server to recurse
+        recursion_desired: false,
+        client_cookie: Some(client_cookie),
+        server_cookie: config.server_cookies.get(name_server),
+        randomize_case: config.randomize_case,
+        ..Default::default()
+    };
+    let query = build_query_with_options(domain_name, type_field, &options);
+    #[cfg(feature = "tracing")]
+    let _span = query_span(&query, name_server, domain_name, type_field).entered();
+    let packet = send_query_with_fallback(name_server, config, deadline, &query)?;
+    validate_edns_version(&packet)?;
+    validate_cookie(&packet, &client_cookie, name_server, &config.server_cookies)?;
+    let query = DNSPacket::from(&query)?;
+    validate_question(&query, &packet)?;
+    if config.randomize_case {
+        validate_question_case(&query, &packet)?;
+    }
+    if packet.is_lame_delegation() {
+        // an error makes the caller move on to the next name server
+        return Err(Error::other(format!(
+            "Lame delegation for {} at {}",
+            domain_name.string, name_server
+        )));
+    }
+    log::debug!(
+        "EDNS version of {}: {:?}",
+        name_server,
+        packet.edns_version()
+    );
+    Ok(packet)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::testutil::{
+        a, answer, config, message, name_rr, rcode, record, referral, rr, start_servers, Handler,
+        MockServer,
+    };
+
+    #[test]
+    fn resolved_addresses_follow_cname_chain() {
+        let body = [
+            record(
+                "www.example.com",
+                5,
+                &DomainName::from("cname.example.com").to_bytes(),
+            ),
+            record(
+                "cname.example.com",
+                5,
+                &DomainName::from("host.example.com").to_bytes(),
+            ),
+            record("host.example.com", 1, &[192, 0, 2, 1]),
+        ]
+        .concat();
+        let mut packet = DNSPacket::from(&message([0, 3, 0, 0], &body)).unwrap();
+        packet.questions.push(DNSQuestion {
+            name: DomainName::from("www.example.com"),
+            type_field: QType::Type(TypeField::A),
+            class: ClassField::IN,
+        });
+        assert_eq!(
+            packet.resolved_addresses(),
+            vec![Ipv4Addr::new(192, 0, 2, 1)]
+        );
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn emits_a_span_per_query() {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        use std::sync::Mutex;
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata};
+
+        /// Records the fields of the "query" spans.
+        #[derive(Default)]
+        struct Spans {
+            next_id: AtomicU64,
+            servers: Mutex<Vec<String>>,
+        }
+        struct ServerField<'a>(&'a mut Option<String>);
+        impl tracing::field::Visit for ServerField<'_> {
+            fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn fmt::Debug) {
+                if field.name() == "server" {
+                    *self.0 = Some(format!("{value:?}"));
+                }
+            }
+        }
+        impl tracing::Subscriber for &'static Spans {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+            fn new_span(&self, span: &Attributes<'_>) -> Id {
+                if span.metadata().name() == "query" {
+                    let mut server = None;
+                    span.record(&mut ServerField(&mut server));
+                    self.servers.lock().unwrap().push(server.unwrap());
+                }
+                Id::from_u64(self.next_id.fetch_add(1, Ordering::Relaxed) + 1)
+            }
+            fn record(&self, _: &Id, _: &Record<'_>) {}
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+            fn event(&self, _: &Event<'_>) {}
+            fn enter(&self, _: &Id) {}
+            fn exit(&self, _: &Id) {}
+        }
+
+        let spans: &'static Spans = Box::leak(Box::default());
+        tracing::subscriber::with_default(spans, || {
+            for server in [Ipv4Addr::new(192, 0, 2, 1), Ipv4Addr::new(192, 0, 2, 2)] {
+                let domain_name = DomainName::from("example.com");
+                let query = build_query(&domain_name, TypeField::A);
+                let _span = query_span(&query, server.into(), &domain_name, TypeField::A).entered();
+            }
+        });
+        assert_eq!(
+            *spans.servers.lock().unwrap(),
+            vec!["192.0.2.1".to_string(), "192.0.2.2".to_string()]
+        );
+    }
+
+    fn response_with_cookie(cookie: Option<&[u8]>) -> DNSPacket {
+        let mut opt = OptRecord::new(1232);
+        if let Some(cookie) = cookie {
+            opt.options
+                .push(EdnsOption::from_parts(edns::COOKIE_OPTION_CODE, cookie));
+        }
+        response_with_opt(opt)
+    }
+
+    fn response_with_opt(opt: OptRecord) -> DNSPacket {
+        let opt = DNSRecord::from_reader(&mut Cursor::new(&opt.to_bytes()[..])).unwrap();
+        let header = DNSHeader::from_bytes(&[0; DNS_HEADER_SIZE]).unwrap();
+        DNSPacket::new(header, vec![], vec![], vec![], vec![opt])
+    }
+
+    #[test]
+    fn cookie_is_required_once_the_server_returned_one() {
+        let client_cookie = [1; edns::CLIENT_COOKIE_LEN];
+        let cookie = [client_cookie, [2; 8]].concat();
+        let server = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+        let server_cookies = ServerCookies::default();
+
+        // servers that support EDNS but not cookies are fine
+        let without_cookie = response_with_cookie(None);
+        assert!(validate_cookie(&without_cookie, &client_cookie, server, &server_cookies).is_ok());
+
+        let with_cookie = response_with_cookie(Some(&cookie));
+        assert!(validate_cookie(&with_cookie, &client_cookie, server, &server_cookies).is_ok());
+        assert_eq!(server_cookies.get(server), Some(vec![2; 8]));
+        assert!(validate_cookie(&without_cookie, &client_cookie, server, &server_cookies).is_err());
+
+        let other_server = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2));
+        assert!(validate_cookie(
+            &without_cookie,
+            &client_cookie,
+            other_server,
+            &server_cookies
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn sends_the_server_cookie_back_on_later_queries() {
+        let server_cookie = [2; 8];
+        let handler: Handler = Box::new(move |query| {
+            let client_cookie = query
+                .get_opt()?
+                .cookie()?
+                .get(..edns::CLIENT_COOKIE_LEN)?
+                .to_vec();
+            let opt = OptRecord::new(1232)
+                .with_cookies(client_cookie.try_into().unwrap(), &server_cookie);
+            let opt = DNSRecord::from_reader(&mut Cursor::new(&opt.to_bytes()[..])).unwrap();
+            let response = answer(query, vec![a("example.com", Ipv4Addr::new(192, 0, 2, 1))]);
+            Some(DNSPacket::new(
+                response.header,
+                response.questions,
+                response.answers,
+                vec![],
+                vec![opt],
+            ))
+        });
+        let (port, servers) = start_servers(vec![(Ipv4Addr::LOCALHOST.into(), handler)]);
+        let config = config(port, Ipv4Addr::LOCALHOST);
+        let domain_name = DomainName::from("example.com");
+        resolve_records_with_config(&domain_name, TypeField::A, &config).unwrap();
+        resolve_records_with_config(&domain_name, TypeField::A, &config).unwrap();
+
+        let queries = servers[0].queries();
+        let first = queries[0].get_opt().unwrap().cookie().unwrap();
+        assert_eq!(first.len(), edns::CLIENT_COOKIE_LEN);
+        let second = queries[1].get_opt().unwrap().cookie().unwrap();
+        assert_eq!(second, [&first[..], &server_cookie].concat());
+    }
+
+    #[test]
+    fn parses_header_only_packet() {
+        let packet = DNSPacket::from(&message([0, 0, 0, 0], &[])).unwrap();
+        assert!(packet.is_empty());
+        assert!(packet.get_answer().is_none());
+    }
+
+    #[test]
+    fn min_answer_ttl_is_smallest_ttl() {
+        let body = [[192, 0, 2, 1], [192, 0, 2, 2], [192, 0, 2, 3]]
+            .map(|x| record("example.com", 1, &x))
+            .concat();
+        let mut packet = DNSPacket::from(&message([0, 3, 0, 0], &body)).unwrap();
+        for (answer, ttl) in packet.answers.iter_mut().zip([300, 60, 120]) {
+            answer.ttl = ttl;
+        }
+        assert_eq!(packet.min_answer_ttl(), Some(60));
+        let empty = DNSPacket::from(&message([0, 0, 0, 0], &[])).unwrap();
+        assert_eq!(empty.min_answer_ttl(), None);
+    }
+
+    #[test]
+    fn builds_notify() {
+        let zone = DomainName::from("example.com");
+        let bytes = build_notify(&zone);
+        // the opcode takes bits 1-4 of the third byte
+        assert_eq!((bytes[2] >> 3) & 0x0f, 4);
+        let packet = DNSPacket::from(&bytes).unwrap();
+        assert!(packet.is_notify());
+        assert_eq!(packet.questions[0].name.string, zone.string);
+        assert!(!DNSPacket::from(&build_query(&zone, TypeField::SOA))
+            .unwrap()
+            .is_notify());
+    }
+
+    #[test]
+    fn empty_name_and_dot_are_the_root() {
+        for name in ["", "."] {
+            let root = DomainName::from(name);
+            assert_eq!(root.to_bytes(), vec![0]);
+            assert_eq!(root.string, "");
+        }
+        assert_eq!(
+            DomainName::from("example.com.").to_bytes(),
+            DomainName::from("example.com").to_bytes()
+        );
+    }
+
+    #[test]
+    fn ipv6_arpa_name() {
+        let name = DomainName::from_ipv6_arpa("2001:db8::1".parse().unwrap());
+        assert_eq!(
+            name.string,
+            "1.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.ip6.arpa"
+        );
+        assert_eq!(name.string.split(".").count(), 34);
+    }
+
+    fn answer_with_owner_pointer(pointer: u16) -> Result<DNSPacket, Error> {
+        let mut answer = (0xc000 | pointer).to_be_bytes().to_vec();
+        answer.extend_from_slice(&record("example.com", 1, &[1, 2, 3, 4])[13..]);
+        DNSPacket::from(&message([0, 1, 0, 0], &answer))
+    }
+
+    #[test]
+    fn rejects_pointer_into_header() {
+        let error = answer_with_owner_pointer(0).unwrap_err();
+        assert_eq!(
+            DnsError::from_io(&error),
+            Some(&DnsError::BadPointer {
+                pointer: 0,
+                offset: DNS_HEADER_SIZE as u64
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_pointer_past_end() {
+        let error = answer_with_owner_pointer(0x3fff).unwrap_err();
+        assert!(matches!(
+            DnsError::from_io(&error),
+            Some(DnsError::BadPointer {
+                pointer: 0x3fff,
+                ..
+            })
+        ));
+    }
+
+    fn query_opt(options: &QueryOptions) -> OptRecord {
+        let query =
+            build_query_with_options(&DomainName::from("example.com"), TypeField::A, options);
+        let packet = DNSPacket::from(&query).unwrap();
+        packet.additionals[0].opt.clone().unwrap()
+    }
+
+    #[test]
+    fn sets_dnssec_ok_bit() {
+        let opt = query_opt(&QueryOptions {
+            dnssec_ok: true,
+            ..Default::default()
+        });
+        assert!(opt.dnssec_ok());
+        // DO is the top bit of the flags in the TTL field
+        assert_eq!(opt.ttl() & 0x8000, 0x8000);
+
+        let query = build_query(&DomainName::from("example.com"), TypeField::A);
+        assert!(DNSPacket::from(&query).unwrap().additionals.is_empty());
+    }
+
+    #[test]
+    fn rejects_rdata_longer_than_message() {
+        let mut bytes = record("example.com", 1, &[1, 2, 3, 4]);
+        let length_offset = bytes.len() - 6;
+        bytes[length_offset..length_offset + 2].copy_from_slice(&u16::MAX.to_be_bytes());
+
+        let error = DNSRecord::from_reader(&mut Cursor::new(&bytes[..])).unwrap_err();
+        assert_eq!(
+            DnsError::from_io(&error),
+            Some(&DnsError::RdataOverflow {
+                length: u16::MAX,
+                offset: length_offset as u64,
+            })
+        );
+    }
+
+    #[test]
+    fn pads_query_to_block_size() {
+        for name in ["a.com", "a-much-longer-name.example.com"] {
+            let options = QueryOptions {
+                padding_block_size: Some(128),
+                ..Default::default()
+            };
+            let query = build_query_with_options(&DomainName::from(name), TypeField::A, &options);
+            assert_eq!(query.len() % 128, 0);
+            let opt = DNSPacket::from(&query).unwrap().additionals[0]
+                .opt
+                .clone()
+                .unwrap();
+            assert!(opt.option(edns::PADDING_OPTION_CODE).is_some());
+        }
+    }
+
+    #[test]
+    fn response_echoes_query() {
+        let query_bytes = build_query(&DomainName::from("example.com"), TypeField::A);
+        let query = DNSPacket::from(&query_bytes).unwrap();
+        let answers = vec![a("example.com", Ipv4Addr::new(192, 0, 2, 1))];
+        let response = DNSPacket::response_to(&query, answers.clone());
+
+        let response = DNSPacket::from(&response.to_bytes()).unwrap();
+        assert_eq!(response.header.id, query.header.id);
+        assert!(response.header.flags.is_response());
+        assert_eq!(response.questions.len(), 1);
+        assert_eq!(
+            response.questions[0].to_bytes(),
+            query.questions[0].to_bytes()
+        );
+        assert_eq!(response.header.num_answers, 1);
+        assert_eq!(response.answers[0].to_bytes(), answers[0].to_bytes());
+    }
+
+    #[test]
+    fn keeps_non_utf8_label() {
+        let mut bytes = vec![3, b'a', 0xff, b'b'];
+        bytes.extend_from_slice(&DomainName::from("example.com").to_bytes());
+        let name = DomainName::from_reader(&mut Cursor::new(&bytes[..])).unwrap();
+        assert_eq!(name.raw_labels[0], vec![b'a', 0xff, b'b']);
+        assert_eq!(name.string, "a\\255b.example.com");
+        assert_eq!(name.to_bytes(), bytes);
+        assert_eq!(DomainName::from(&name.string), name);
+    }
+
+    #[test]
+    fn escaped_dot_stays_in_its_label() {
+        let name = DomainName::from(r"foo\.bar.example.com");
+        let labels: Vec<&[u8]> = vec![b"foo.bar", b"example", b"com"];
+        assert_eq!(name.raw_labels, labels);
+        assert_eq!(&name.to_bytes()[..8], b"\x07foo.bar");
+        assert_eq!(name.string, r"foo\.bar.example.com");
+    }
+
+    #[test]
+    fn decimal_escapes() {
+        let name = DomainName::parse(r"a\065b\255.example").unwrap();
+        assert_eq!(name.raw_labels[0], b"aAb\xff");
+        assert_eq!(name.string, r"aAb\255.example");
+    }
+
+    #[test]
+    fn from_keeps_invalid_escapes() {
+        let name = DomainName::from(r"\256.example\");
+        let labels: Vec<&[u8]> = vec![br"\256", br"example\"];
+        assert_eq!(name.raw_labels, labels);
+    }
+
+    #[test]
+    fn validates_query() {
+        let query = build_query(&DomainName::from("example.com"), TypeField::A);
+        let query = DNSPacket::from(&query).unwrap();
+        assert!(query.validate(true).is_ok());
+        assert!(query.validate(false).is_err());
+    }
+
+    #[test]
+    fn rejects_response_where_query_expected() {
+        let response = DNSPacket::from(&message([0, 0, 0, 0], &[])).unwrap();
+        assert!(response.validate(false).is_ok());
+        assert_eq!(
+            response.validate(true).unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn rejects_count_mismatch() {
+        let mut response = DNSPacket::from(&message([0, 0, 0, 0], &[])).unwrap();
+        response.header.num_answers = 1;
+        assert!(response.validate(false).is_err());
+    }
+
+    #[test]
+    fn glue_matches_name_server() {
+        let query =
+            DNSPacket::from(&build_query(&DomainName::from("example.com"), TypeField::A)).unwrap();
+        let glue = vec![
+            a("ns1.example.com", Ipv4Addr::new(192, 0, 2, 1)),
+            a("ns2.example.com", Ipv4Addr::new(192, 0, 2, 2)),
+        ];
+        let packet = referral(
+            &query,
+            "example.com",
+            &["ns1.example.com", "ns2.example.com"],
+            glue,
+        );
+        assert_eq!(
+            packet.glue_for(&DomainName::from("ns2.example.com")),
+            Some(Ipv4Addr::new(192, 0, 2, 2))
+        );
+        assert_eq!(
+            packet.glue_for(&DomainName::from("NS1.example.com")),
+            Some(Ipv4Addr::new(192, 0, 2, 1))
+        );
+        assert_eq!(packet.glue_for(&DomainName::from("ns3.example.com")), None);
+    }
+
+    #[test]
+    fn reads_truncated_flag() {
+        let mut data = message([0, 0, 0, 0], &[]);
+        assert!(!DNSPacket::from(&data).unwrap().is_truncated());
+        // TC is the second lowest bit of the third byte
+        data[2] |= 0b10;
+        assert!(DNSPacket::from(&data).unwrap().is_truncated());
+    }
+
+    #[test]
+    fn parses_question_with_axfr_qtype() {
+        let mut question = DomainName::from("example.com").to_bytes();
+        question.extend_from_slice(&252u16.to_be_bytes());
+        question.extend_from_slice(&1u16.to_be_bytes());
+        let packet = DNSPacket::from(&message([1, 0, 0, 0], &question)).unwrap();
+        assert_eq!(packet.questions[0].type_field, QType::AXFR);
+        assert_eq!(packet.questions[0].to_bytes(), question);
+
+        // the query-only values are still rejected in records
+        let record = record("example.com", 252, &[]);
+        assert!(DNSPacket::from(&message([0, 1, 0, 0], &record)).is_err());
+    }
+
+    #[test]
+    fn new_sets_header_counts() {
+        let header = DNSHeader::from_bytes(&[0; DNS_HEADER_SIZE]).unwrap();
+        let question = DNSQuestion {
+            name: DomainName::from("example.com"),
+            type_field: TypeField::A.into(),
+            class: ClassField::IN,
+        };
+        let packet = DNSPacket::new(
+            header,
+            vec![question],
+            vec![a("example.com", Ipv4Addr::new(192, 0, 2, 1))],
+            vec![name_rr("example.com", TypeField::NS, "ns1.example.com")],
+            vec![
+                a("ns1.example.com", Ipv4Addr::new(192, 0, 2, 2)),
+                a("ns2.example.com", Ipv4Addr::new(192, 0, 2, 3)),
+            ],
+        );
+        let header = &packet.header;
+        assert_eq!(
+            (
+                header.num_questions,
+                header.num_answers,
+                header.num_authorities,
+                header.num_additionals
+            ),
+            (1, 1, 1, 2)
+        );
+        assert!(packet.validate(false).is_err());
+        assert!(packet.validate(true).is_ok());
+    }
+
+    #[test]
+    fn socket_addrs_of_mixed_answers() {
+        let answers = vec![
+            a("example.com", Ipv4Addr::new(192, 0, 2, 1)),
+            rr(
+                "example.com",
+                TypeField::AAAA,
+                &"2001:db8::1".parse::<Ipv6Addr>().unwrap().octets(),
+            ),
+            name_rr("example.com", TypeField::NS, "ns1.example.com"),
+        ];
+        let header = DNSHeader::from_bytes(&[0; DNS_HEADER_SIZE]).unwrap();
+        let packet = DNSPacket::new(header, vec![], answers, vec![], vec![]);
+        assert_eq!(
+            packet.socket_addrs(443),
+            vec![
+                "192.0.2.1:443".parse::<SocketAddr>().unwrap(),
+                "[2001:db8::1]:443".parse().unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_unexpected_edns_version() {
+        let response = response_with_opt(OptRecord::new(1232));
+        assert_eq!(response.edns_version(), Some(0));
+        assert!(validate_edns_version(&response).is_ok());
+
+        let mut opt = OptRecord::new(1232);
+        opt.version = 1;
+        let response = response_with_opt(opt);
+        assert_eq!(response.edns_version(), Some(1));
+        let error = validate_edns_version(&response).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Unsupported);
+    }
+
+    #[test]
+    fn rejects_badvers() {
+        let mut opt = OptRecord::new(1232);
+        // the upper 8 bits of the 12-bit rcode
+        opt.extended_rcode = (edns::BADVERS >> 4) as u8;
+        let response = response_with_opt(opt);
+        assert_eq!(response.extended_rcode(), edns::BADVERS);
+        assert!(validate_edns_version(&response).is_err());
+    }
+
+    #[test]
+    fn detects_lame_delegation() {
+        let query =
+            DNSPacket::from(&build_query(&DomainName::from("example.com"), TypeField::A)).unwrap();
+        // neither authoritative nor a referral
+        assert!(DNSPacket::response_to(&query, vec![]).is_lame_delegation());
+        assert!(!answer(&query, vec![]).is_lame_delegation());
+        let referral = referral(&query, "example.com", &["ns1.example.com"], vec![]);
+        assert!(!referral.is_lame_delegation());
+    }
+
+    fn answer_a(address: Ipv4Addr) -> Handler {
+        Box::new(move |query| {
+            let name = query.questions[0].name.string.clone();
+            Some(answer(query, vec![a(&name, address)]))
+        })
+    }
+
+    #[test]
+    fn queries_server_on_custom_port() {
+        let (port, servers) = start_servers(vec![(
+            Ipv4Addr::LOCALHOST.into(),
+            answer_a(Ipv4Addr::new(192, 0, 2, 1)),
+        )]);
+        assert_ne!(port, DEFAULT_DNS_PORT);
+        assert_eq!(ResolverConfig::default().port, DEFAULT_DNS_PORT);
+
+        let response = resolve_with_server_on_port(
+            &DomainName::from("example.com"),
+            TypeField::A,
+            Ipv4Addr::LOCALHOST,
+            port,
+        )
+        .unwrap();
+        assert_eq!(
+            response.get_answer().unwrap().ipv4,
+            Some(vec![Ipv4Addr::new(192, 0, 2, 1)])
+        );
+        assert!(servers[0].queries()[0].header.flags.recursion_desired());
+    }
+
+    #[test]
+    fn resolver_with_custom_timeout_and_root_hint() {
+        let (port, servers) = start_servers(vec![(
+            Ipv4Addr::LOCALHOST.into(),
+            answer_a(Ipv4Addr::new(192, 0, 2, 1)),
+        )]);
+        let resolver = Resolver::new(ResolverConfig {
+            timeout: Duration::from_millis(300),
+            root_hints: vec![Ipv4Addr::LOCALHOST],
+            port,
+            ..Default::default()
+        });
+        assert_eq!(resolver.config().timeout, Duration::from_millis(300));
+        assert_eq!(resolver.config().root_hints, vec![Ipv4Addr::LOCALHOST]);
+
+        let address = resolver
+            .resolve(&DomainName::from("example.com"), TypeField::A)
+            .unwrap();
+        assert_eq!(address, Ipv4Addr::new(192, 0, 2, 1));
+        assert_eq!(servers[0].queries().len(), 1);
+    }
+
+    /// A root server delegating `zone` to `ns.<zone>`, whose glue is `address`.
+    fn delegation(zone: &'static str, address: Ipv4Addr) -> Handler {
+        delegation_to_servers(zone, vec![address])
+    }
+
+    /// A root server delegating `zone` to `ns1.<zone>`, `ns2.<zone>`... in this
+    /// order, whose glue are `addresses`.
+    fn delegation_to_servers(zone: &'static str, addresses: Vec<Ipv4Addr>) -> Handler {
+        Box::new(move |query| {
+            let names: Vec<String> = (1..=addresses.len())
+                .map(|i| format!("ns{i}.{zone}"))
+                .collect();
+            let glue = names
+                .iter()
+                .zip(&addresses)
+                .map(|(name, address)| a(name, *address))
+                .collect();
+            let names: Vec<&str> = names.iter().map(String::as_str).collect();
+            Some(referral(query, zone, &names, glue))
+        })
+    }
+
+    #[test]
+    fn parallel_queries_use_fastest_server() {
+        let slow = Ipv4Addr::new(127, 0, 0, 2);
+        let fast = Ipv4Addr::new(127, 0, 0, 3);
+        let (port, _servers) = start_servers(vec![
+            (
+                Ipv4Addr::LOCALHOST.into(),
+                delegation_to_servers("example.com", vec![slow, fast]),
+            ),
+            (slow.into(), Box::new(|_| None)),
+            (fast.into(), answer_a(Ipv4Addr::new(192, 0, 2, 1))),
+        ]);
+        let config = ResolverConfig {
+            parallel_queries: true,
+            ..config(port, Ipv4Addr::LOCALHOST)
+        };
+
+        let start = Instant::now();
+        let address = Resolver::new(config)
+            .resolve(&DomainName::from("example.com"), TypeField::A)
+            .unwrap();
+        assert_eq!(address, Ipv4Addr::new(192, 0, 2, 1));
+        // serially we'd have waited for the first server to time out
+        assert!(start.elapsed() < Duration::from_millis(400));
+    }
+
+    #[test]
+    fn restarts_at_cname_target() {
+        let authoritative: Handler = Box::new(|query| {
+            let name = query.questions[0].name.string.clone();
+            let record = if name == "www.example.com" {
+                name_rr(&name, TypeField::CNAME, "host.example.com")
+            } else {
+                a(&name, Ipv4Addr::new(192, 0, 2, 1))
+            };
+            Some(answer(query, vec![record]))
+        });
+        let (port, servers) = start_servers(vec![
+            (
+                Ipv4Addr::LOCALHOST.into(),
+                delegation("example.com", Ipv4Addr::new(127, 0, 0, 2)),
+            ),
+            (Ipv4Addr::new(127, 0, 0, 2).into(), authoritative),
+        ]);
+        let address = Resolver::new(config(port, Ipv4Addr::LOCALHOST))
+            .resolve(&DomainName::from("www.example.com"), TypeField::A)
+            .unwrap();
+        assert_eq!(address, Ipv4Addr::new(192, 0, 2, 1));
+        let names: Vec<String> = servers[1]
+            .queries()
+            .iter()
+            .map(|x| x.questions[0].name.string.clone())
+            .collect();
+        assert_eq!(names, ["www.example.com", "host.example.com"]);
+    }
+
+    #[test]
+    fn skips_lame_name_server() {
+        let lame = Ipv4Addr::new(127, 0, 0, 2);
+        let good = Ipv4Addr::new(127, 0, 0, 3);
+        let (port, servers) = start_servers(vec![
+            (
+                Ipv4Addr::LOCALHOST.into(),
+                delegation_to_servers("example.com", vec![lame, good]),
+            ),
+            // neither authoritative nor a referral
+            (
+                lame.into(),
+                Box::new(|query| Some(DNSPacket::response_to(query, vec![]))),
+            ),
+            (good.into(), answer_a(Ipv4Addr::new(192, 0, 2, 1))),
+        ]);
+        let address = Resolver::new(config(port, Ipv4Addr::LOCALHOST))
+            .resolve(&DomainName::from("example.com"), TypeField::A)
+            .unwrap();
+        assert_eq!(address, Ipv4Addr::new(192, 0, 2, 1));
+        assert_eq!(servers[1].queries().len(), 1);
+    }
+
+    fn mx(name: &str, preference: u16, exchange: &str) -> DNSRecord {
+        let rdata = MxRecord {
+            preference,
+            exchange: DomainName::from(exchange),
+        };
+        rr(name, TypeField::MX, &rdata.to_bytes())
+    }
+
+    #[test]
+    fn resolve_records_returns_all_mx() {
+        let authoritative: Handler = Box::new(|query| {
+            let mut response = answer(
+                query,
+                vec![
+                    mx("example.com", 10, "mx1.example.com"),
+                    mx("example.com", 20, "mx2.example.com"),
+                    mx("example.com", 30, "mx3.example.com"),
+                ],
+            );
+            response
+                .additionals
+                .push(a("mx1.example.com", Ipv4Addr::new(192, 0, 2, 1)));
+            response.header.num_additionals = 1;
+            Some(response)
+        });
+        let (port, _servers) = start_servers(vec![
+            (
+                Ipv4Addr::LOCALHOST.into(),
+                delegation("example.com", Ipv4Addr::new(127, 0, 0, 2)),
+            ),
+            (Ipv4Addr::new(127, 0, 0, 2).into(), authoritative),
+        ]);
+        let records = Resolver::new(config(port, Ipv4Addr::LOCALHOST))
+            .resolve_records(&DomainName::from("example.com"), TypeField::MX)
+            .unwrap();
+        let exchanges: Vec<_> = records
+            .iter()
+            .map(|x| x.mx.as_ref().unwrap().exchange.string.as_str())
+            .collect();
+        assert_eq!(
+            exchanges,
+            ["mx1.example.com", "mx2.example.com", "mx3.example.com"]
+        );
+    }
+
+    #[test]
+    fn ignores_out_of_bailiwick_glue() {
+        let com = Ipv4Addr::new(127, 0, 0, 2);
+        let poisoned = Ipv4Addr::new(127, 0, 0, 3);
+        let legitimate = Ipv4Addr::new(127, 0, 0, 4);
+        let root: Handler = Box::new(move |query| {
+            let name = query.questions[0].name.string.clone();
+            if name == "ns.other.net" {
+                return Some(answer(query, vec![a(&name, legitimate)]));
+            }
+            let glue = vec![a("ns.com", com)];
+            Some(referral(query, "com", &["ns.com"], glue))
+        });
+        // the servers of com can't vouch for the address of a name in net
+        let com_server: Handler = Box::new(move |query| {
+            let glue = vec![a("ns.other.net", poisoned)];
+            Some(referral(query, "example.com", &["ns.other.net"], glue))
+        });
+        let (port, servers) = start_servers(vec![
+            (Ipv4Addr::LOCALHOST.into(), root),
+            (com.into(), com_server),
+            (poisoned.into(), answer_a(Ipv4Addr::new(192, 0, 2, 66))),
+            (legitimate.into(), answer_a(Ipv4Addr::new(192, 0, 2, 1))),
+        ]);
+        let address = Resolver::new(config(port, Ipv4Addr::LOCALHOST))
+            .resolve(&DomainName::from("example.com"), TypeField::A)
+            .unwrap();
+        assert_eq!(address, Ipv4Addr::new(192, 0, 2, 1));
+        assert!(servers[2].queries().is_empty());
+    }
+
+    #[test]
+    fn keeps_txt_string_boundaries() {
+        let strings = ["v=spf1", "include:_spf.example.com", "~all"];
+        let mut rdata = vec![];
+        for string in strings {
+            rdata.push(string.len() as u8);
+            rdata.extend_from_slice(string.as_bytes());
+        }
+        let data = message([0, 1, 0, 0], &record("example.com", 16, &rdata));
+        let packet = DNSPacket::from(&data).unwrap();
+        assert_eq!(
+            packet.answers[0].txt,
+            Some(strings.map(|x| x.as_bytes().to_vec()).to_vec())
+        );
+    }
+
+    #[test]
+    fn keeps_txt_bytes_that_are_not_utf8() {
+        let rdata = b"\x04a\xffb\"".to_vec();
+        let data = message([0, 1, 0, 0], &record("example.com", 16, &rdata));
+        let packet = DNSPacket::from(&data).unwrap();
+        assert_eq!(packet.answers[0].txt, Some(vec![b"a\xffb\"".to_vec()]));
+        assert!(packet.answers[0].to_string().ends_with("\"a\\255b\\\"\""));
+    }
+
+    #[test]
+    fn builds_iquery() {
+        let bytes = build_iquery(&a("example.com", Ipv4Addr::new(192, 0, 2, 1)));
+        assert_eq!((bytes[2] >> 3) & 0x0f, 1);
+        let packet = DNSPacket::from(&bytes).unwrap();
+        assert_eq!(packet.header.flags.opcode(), flags::OpCode::IQuery);
+        assert_eq!(
+            packet.answers[0].ipv4,
+            Some(vec![Ipv4Addr::new(192, 0, 2, 1)])
+        );
+        assert!(packet.validate(true).is_ok());
+    }
+
+    #[test]
+    fn from_prefix_reports_bytes_consumed() {
+        let data = message([0, 1, 0, 0], &record("example.com", 1, &[1, 2, 3, 4]));
+        let (_, consumed) = DNSPacket::from_prefix(&data).unwrap();
+        assert_eq!(consumed, data.len());
+
+        let mut two = data.clone();
+        two.extend_from_slice(&message([0, 0, 0, 0], &[]));
+        let (packet, consumed) = DNSPacket::from_prefix(&two).unwrap();
+        assert_eq!(consumed, data.len());
+        assert_eq!(packet.answers.len(), 1);
+    }
+
+    #[test]
+    fn round_robin_cycles_through_addresses() {
+        let addresses = [1, 2, 3].map(|x| Ipv4Addr::new(192, 0, 2, x));
+        let authoritative: Handler = Box::new(move |query| {
+            let answers = addresses.iter().map(|x| a("example.com", *x)).collect();
+            Some(answer(query, answers))
+        });
+        let (port, _servers) = start_servers(vec![(Ipv4Addr::LOCALHOST.into(), authoritative)]);
+        let resolver = Resolver::new(ResolverConfig {
+            address_selection: AddressSelection::RoundRobin,
+            ..config(port, Ipv4Addr::LOCALHOST)
+        });
+        let selected: Vec<Ipv4Addr> = (0..4)
+            .map(|_| {
+                resolver
+                    .resolve(&DomainName::from("example.com"), TypeField::A)
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(selected, [addresses.to_vec(), vec![addresses[0]]].concat());
+    }
+
+    #[test]
+    fn decodes_spf_record() {
+        let rdata = b"\x0bv=spf1 -all".to_vec();
+        let data = message([0, 1, 0, 0], &record("example.com", 99, &rdata));
+        let packet = DNSPacket::from(&data).unwrap();
+        assert_eq!(packet.answers[0].type_field, TypeField::SPF);
+        assert_eq!(packet.answers[0].txt, Some(vec![b"v=spf1 -all".to_vec()]));
+    }
+
+    #[test]
+    fn invalid_type_error_has_offset() {
+        let data = message([0, 1, 0, 0], &record("example.com", 43, &[0; 6]));
+        let error = DNSPacket::from(&data).unwrap_err();
+        // the TYPE follows the 13 bytes of the owner name
+        let offset = (DNS_HEADER_SIZE + 13) as u64;
+        assert_eq!(
+            DnsError::from_io(&error),
+            Some(&DnsError::InvalidType { value: 43, offset })
+        );
+        assert!(error.to_string().contains(&format!("offset {offset}")));
+    }
+
+    #[test]
+    fn dedup_answers_collapses_duplicates() {
+        let first = a("example.com", Ipv4Addr::new(192, 0, 2, 1));
+        let second = a("example.com", Ipv4Addr::new(192, 0, 2, 2));
+        let header = DNSHeader::from_bytes(&[0; DNS_HEADER_SIZE]).unwrap();
+        let mut packet = DNSPacket::new(
+            header,
+            vec![],
+            vec![first.clone(), second.clone(), first.clone(), first.clone()],
+            vec![],
+            vec![],
+        );
+        packet.dedup_answers();
+        assert_eq!(packet.answers, vec![first, second]);
+        assert_eq!(packet.header.num_answers, 2);
+    }
+
+    #[test]
+    fn is_subdomain_of() {
+        let www = DomainName::from("www.example.com");
+        assert!(www.is_subdomain_of(&DomainName::from("example.com")));
+        assert!(www.is_subdomain_of(&DomainName::from("EXAMPLE.com")));
+        assert!(www.is_subdomain_of(&www));
+        assert!(www.is_subdomain_of(&DomainName::from(".")));
+        assert!(!www.is_subdomain_of(&DomainName::from("other.com")));
+        // labels are compared whole
+        assert!(!www.is_subdomain_of(&DomainName::from("ample.com")));
+        assert!(!DomainName::from("example.com").is_subdomain_of(&www));
+    }
+
+    #[test]
+    fn parse_rejects_invalid_escapes() {
+        for (name, offset) in [
+            (r"\256.example", 0),
+            (r"a\25x.example", 1),
+            (r"example.a\25", 9),
+            (r"example\", 7),
+        ] {
+            assert_eq!(
+                DomainName::parse(name).unwrap_err(),
+                DnsError::InvalidEscape { offset },
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn parse_rejects_long_label() {
+        let name = format!("{}.example.com", "a".repeat(64));
+        assert_eq!(
+            DomainName::parse(&name),
+            Err(DnsError::LabelTooLong { length: 64 })
+        );
+        assert!(DomainName::parse(&format!("{}.example.com", "a".repeat(63))).is_ok());
+    }
+
+    #[test]
+    fn parse_rejects_long_name() {
+        let name = format!("{}.{}", vec!["a".repeat(50); 5].join("."), "a".repeat(45));
+        assert_eq!(name.len(), 300);
+        assert!(matches!(
+            DomainName::parse(&name),
+            Err(DnsError::NameTooLong { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_rejects_empty_label() {
+        assert_eq!(
+            DomainName::parse("www..example.com"),
+            Err(DnsError::EmptyLabel)
+        );
+    }
+
+    #[test]
+    fn classifies_responses() {
+        let query = DNSPacket::from(&build_query(
+            &DomainName::from("www.example.com"),
+            TypeField::A,
+        ))
+        .unwrap();
+        let address = a("www.example.com", Ipv4Addr::new(192, 0, 2, 1));
+        let alias = name_rr("www.example.com", TypeField::CNAME, "host.example.com");
+        let soa = SoaRecord {
+            mname: DomainName::from("ns1.example.com"),
+            rname: DomainName::from("hostmaster.example.com"),
+            serial: 1,
+            refresh: 7200,
+            retry: 3600,
+            expire: 1209600,
+            minimum: 300,
+        };
+        let mut nodata = answer(&query, vec![]);
+        nodata
+            .authorities
+            .push(rr("example.com", TypeField::SOA, &soa.to_bytes()));
+
+        let cases = [
+            (answer(&query, vec![address.clone()]), ResponseKind::Answer),
+            (
+                answer(&query, vec![alias.clone(), address]),
+                ResponseKind::Answer,
+            ),
+            (answer(&query, vec![alias]), ResponseKind::CnameChain),
+            (
+                referral(&query, "example.com", &["ns1.example.com"], vec![]),
+                ResponseKind::Referral,
+            ),
+            (nodata, ResponseKind::NoData),
+            (answer(&query, vec![]), ResponseKind::NoData),
+            (rcode(&query, flags::RCODE_NXDOMAIN), ResponseKind::NXDomain),
+        ];
+        for (response, kind) in cases {
+            assert_eq!(response.classify(), kind, "{:?}", response);
+        }
+    }
+
+    /// Answers like `handler`, after `delay`.
+    fn slow(delay: Duration, handler: Handler) -> Handler {
+        Box::new(move |query| {
+            thread::sleep(delay);
+            handler(query)
+        })
+    }
+
+    #[test]
+    fn total_timeout_stops_slow_resolution() {
+        let delay = Duration::from_millis(150);
+        let com = Ipv4Addr::new(127, 0, 0, 2);
+        let example = Ipv4Addr::new(127, 0, 0, 3);
+        let (port, _servers) = start_servers(vec![
+            (
+                Ipv4Addr::LOCALHOST.into(),
+                slow(delay, delegation("com", com)),
+            ),
+            (com.into(), slow(delay, delegation("example.com", example))),
+            (
+                example.into(),
+                slow(delay, answer_a(Ipv4Addr::new(192, 0, 2, 1))),
+            ),
+        ]);
+        let resolver = Resolver::new(ResolverConfig {
+            total_timeout: Some(Duration::from_millis(350)),
+            ..config(port, Ipv4Addr::LOCALHOST)
+        });
+
+        let start = Instant::now();
+        // every hop is well within the timeout of a query, but not all three together
+        let error = resolver
+            .resolve(&DomainName::from("example.com"), TypeField::A)
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::TimedOut);
+        assert!(start.elapsed() < delay * 3);
+    }
+
+    #[test]
+    fn to_query_bytes_round_trip() {
+        let options = QueryOptions {
+            recursion_desired: true,
+            ..Default::default()
+        };
+        let query =
+            build_query_with_options(&DomainName::from("example.com"), TypeField::MX, &options);
+        let packet = DNSPacket::from(&query).unwrap();
+        assert_eq!(packet.to_query_bytes(), query);
+
+        let response = answer(&packet, vec![mx("example.com", 10, "mx.example.com")]);
+        assert_eq!(response.to_query_bytes(), query);
+    }
+
+    #[test]
+    fn rejects_response_with_altered_case() {
+        let options = QueryOptions {
+            randomize_case: true,
+            ..Default::default()
+        };
+        let name = DomainName::from("a-rather-long-name.example.com");
+        let query = build_query_with_options(&name, TypeField::A, &options);
+        let query = DNSPacket::from(&query).unwrap();
+        assert!(query.questions[0]
+            .name
+            .string
+            .eq_ignore_ascii_case(&name.string));
+        assert!(validate_question_case(&query, &answer(&query, vec![])).is_ok());
+
+        let mut response = answer(&query, vec![]);
+        let altered = response.questions[0].name.string.to_ascii_uppercase();
+        response.questions[0].name = DomainName::from(&altered);
+        let lowered = query.questions[0].name.string.to_ascii_lowercase();
+        if altered == query.questions[0].name.string {
+            response.questions[0].name = DomainName::from(&lowered);
+        }
+        assert!(validate_question_case(&query, &response).is_err());
+    }
+
+    #[test]
+    fn wire_len_matches_encoding() {
+        let record = a("www.example.com", Ipv4Addr::new(192, 0, 2, 1));
+        assert_eq!(record.wire_len(), record.to_bytes().len());
+        // 17 bytes of name, 10 of fixed fields and 4 of address
+        assert_eq!(record.wire_len(), 31);
+    }
+
+    #[derive(Debug)]
+    struct Reversed;
+    impl NameserverSelector for Reversed {
+        fn order(&self, mut candidates: Vec<IpAddr>) -> Vec<IpAddr> {
+            candidates.reverse();
+            candidates
+        }
+    }
+
+    #[test]
+    fn custom_selector_picks_first_server() {
+        let first = Ipv4Addr::new(127, 0, 0, 2);
+        let last = Ipv4Addr::new(127, 0, 0, 3);
+        let (port, servers) = start_servers(vec![
+            (
+                Ipv4Addr::LOCALHOST.into(),
+                delegation_to_servers("example.com", vec![first, last]),
+            ),
+            (first.into(), answer_a(Ipv4Addr::new(192, 0, 2, 2))),
+            (last.into(), answer_a(Ipv4Addr::new(192, 0, 2, 3))),
+        ]);
+        let resolver = Resolver::new(ResolverConfig {
+            nameserver_selector: Arc::new(Reversed),
+            ..config(port, Ipv4Addr::LOCALHOST)
+        });
+        let address = resolver
+            .resolve(&DomainName::from("example.com"), TypeField::A)
+            .unwrap();
+        assert_eq!(address, Ipv4Addr::new(192, 0, 2, 3));
+        assert!(servers[1].queries().is_empty());
+        assert_eq!(servers[2].queries().len(), 1);
+    }
+
+    #[test]
+    fn iterative_queries_dont_ask_for_recursion() {
+        let (port, servers) = start_servers(vec![
+            (
+                Ipv4Addr::LOCALHOST.into(),
+                delegation("example.com", Ipv4Addr::new(127, 0, 0, 2)),
+            ),
+            (
+                Ipv4Addr::new(127, 0, 0, 2).into(),
+                answer_a(Ipv4Addr::new(192, 0, 2, 1)),
+            ),
+        ]);
+        Resolver::new(config(port, Ipv4Addr::LOCALHOST))
+            .resolve(&DomainName::from("example.com"), TypeField::A)
+            .unwrap();
+        let queries: Vec<DNSPacket> = servers.iter().flat_map(MockServer::queries).collect();
+        assert_eq!(queries.len(), 2);
+        assert!(queries.iter().all(|x| !x.header.flags.recursion_desired()));
+
+        resolve_with_server_on_port(
+            &DomainName::from("example.com"),
+            TypeField::A,
+            Ipv4Addr::LOCALHOST,
+            port,
+        )
+        .unwrap();
+        assert!(servers[0].queries()[1].header.flags.recursion_desired());
+    }
+
+    #[test]
+    fn from_lenient_skips_unknown_type() {
+        let first = record("example.com", 1, &[1, 2, 3, 4]);
+        // DS, which we don't parse
+        let unknown = record("example.com", 43, &[0; 6]);
+        let last = record("example.com", 1, &[5, 6, 7, 8]);
+        let body = [first.clone(), unknown, last].concat();
+        let data = message([0, 3, 0, 0], &body);
+
+        assert!(DNSPacket::from(&data).is_err());
+        let (packet, warnings) = DNSPacket::from_lenient(&data).unwrap();
+        let offset = (DNS_HEADER_SIZE + first.len() + "example.com".len() + 2) as u64;
+        assert_eq!(warnings, vec![Warning::UnknownType { value: 43, offset }]);
+        assert_eq!(packet.header.num_answers, 2);
+        assert_eq!(
+            packet.answers[1].ipv4,
+            Some(vec![Ipv4Addr::new(5, 6, 7, 8)])
+        );
+    }
+
+    #[test]
+    fn from_lenient_skips_question_of_unknown_class() {
+        let mut question = DNSQuestion {
+            name: DomainName::from("example.com"),
+            type_field: TypeField::A.into(),
+            class: ClassField::IN,
+        }
+        .to_bytes();
+        let class_offset = question.len() - 2;
+        question[class_offset..].copy_from_slice(&42u16.to_be_bytes());
+        let answer = record("example.com", 1, &[1, 2, 3, 4]);
+        let data = message([1, 1, 0, 0], &[question, answer].concat());
+
+        let (packet, warnings) = DNSPacket::from_lenient(&data).unwrap();
+        let offset = (DNS_HEADER_SIZE + class_offset) as u64;
+        assert_eq!(warnings, vec![Warning::UnknownClass { value: 42, offset }]);
+        assert!(packet.questions.is_empty());
+        assert_eq!(packet.answers.len(), 1);
+    }
+
+    #[test]
+    fn preferred_mx_has_lowest_preference() {
+        let answers = vec![
+            mx("example.com", 10, "mx1.example.com"),
+            mx("example.com", 20, "mx2.example.com"),
+            mx("example.com", 10, "mx3.example.com"),
+        ];
+        let header = DNSHeader::from_bytes(&[0; DNS_HEADER_SIZE]).unwrap();
+        let packet = DNSPacket::new(header, vec![], answers, vec![], vec![]);
+        for _ in 0..20 {
+            let exchange = &packet.preferred_mx().unwrap().string;
+            assert!(exchange == "mx1.example.com" || exchange == "mx3.example.com");
+        }
+        let empty = DNSPacket::from(&message([0, 0, 0, 0], &[])).unwrap();
+        assert_eq!(empty.preferred_mx(), None);
+    }
+
+    #[test]
+    fn trace_has_one_step_per_delegation() {
+        let com = Ipv4Addr::new(127, 0, 0, 2);
+        let example = Ipv4Addr::new(127, 0, 0, 3);
+        let (port, _servers) = start_servers(vec![
+            (Ipv4Addr::LOCALHOST.into(), delegation("com", com)),
+            (com.into(), delegation("example.com", example)),
+            (example.into(), answer_a(Ipv4Addr::new(192, 0, 2, 1))),
+        ]);
+        let (address, trace) = Resolver::new(config(port, Ipv4Addr::LOCALHOST))
+            .resolve_trace(&DomainName::from("www.example.com"), TypeField::A)
+            .unwrap();
+        assert_eq!(address, Ipv4Addr::new(192, 0, 2, 1));
+        let steps: Vec<(IpAddr, ResponseKind)> = trace.iter().map(|x| (x.server, x.kind)).collect();
+        assert_eq!(
+            steps,
+            vec![
+                (Ipv4Addr::LOCALHOST.into(), ResponseKind::Referral),
+                (com.into(), ResponseKind::Referral),
+                (example.into(), ResponseKind::Answer),
+            ]
+        );
+        assert_eq!(trace[1].authorities[0].name.string, "example.com");
+    }
+
+    #[test]
+    fn ignores_answer_for_another_name() {
+        let authoritative: Handler = Box::new(|query| {
+            Some(answer(
+                query,
+                vec![
+                    a("ns.example.net", Ipv4Addr::new(192, 0, 2, 66)),
+                    a("example.com", Ipv4Addr::new(192, 0, 2, 1)),
+                ],
+            ))
+        });
+        let (port, servers) = start_servers(vec![(Ipv4Addr::LOCALHOST.into(), authoritative)]);
+        let address = Resolver::new(config(port, Ipv4Addr::LOCALHOST))
+            .resolve(&DomainName::from("example.com"), TypeField::A)
+            .unwrap();
+        assert_eq!(address, Ipv4Addr::new(192, 0, 2, 1));
+
+        let query = &servers[0].queries()[0];
+        let response = answer(
+            query,
+            vec![a("ns.example.net", Ipv4Addr::new(192, 0, 2, 66))],
+        );
+        assert_eq!(response.get_answer(), None);
+    }
+
+    fn resolve_with_glue_of_both_families(
+        prefer_ipv6: bool,
+        ipv6_answers: bool,
+    ) -> (Result<Ipv4Addr, Error>, Vec<MockServer>) {
+        let root: Handler = Box::new(|query| {
+            let glue = vec![
+                a("ns.example.com", Ipv4Addr::new(127, 0, 0, 2)),
+                rr(
+                    "ns.example.com",
+                    TypeField::AAAA,
+                    &Ipv6Addr::LOCALHOST.octets(),
+                ),
+            ];
+            Some(referral(query, "example.com", &["ns.example.com"], glue))
+        });
+        let v4: Handler = Box::new(|query| {
+            Some(answer(
+                query,
+                vec![a("example.com", Ipv4Addr::new(192, 0, 2, 4))],
+            ))
+        });
+        let v6: Handler = Box::new(move |query| {
+            ipv6_answers.then(|| answer(query, vec![a("example.com", Ipv4Addr::new(192, 0, 2, 6))]))
+        });
+        let (port, servers) = start_servers(vec![
+            (Ipv4Addr::LOCALHOST.into(), root),
+            (Ipv4Addr::new(127, 0, 0, 2).into(), v4),
+            (Ipv6Addr::LOCALHOST.into(), v6),
+        ]);
+        let config = ResolverConfig {
+            prefer_ipv6,
+            ..config(port, Ipv4Addr::LOCALHOST)
+        };
+        let result = Resolver::new(config).resolve(&DomainName::from("example.com"), TypeField::A);
+        (result, servers)
+    }
+
+    #[test]
+    fn queries_ipv4_glue_first_by_default() {
+        let (result, servers) = resolve_with_glue_of_both_families(false, true);
+        assert_eq!(result.unwrap(), Ipv4Addr::new(192, 0, 2, 4));
+        assert_eq!(servers[1].queries().len(), 1);
+        assert!(servers[2].queries().is_empty());
+    }
+
+    #[test]
+    fn queries_ipv6_glue_first_when_preferred() {
+        let (result, servers) = resolve_with_glue_of_both_families(true, true);
+        assert_eq!(result.unwrap(), Ipv4Addr::new(192, 0, 2, 6));
+        assert!(servers[1].queries().is_empty());
+        assert_eq!(servers[2].queries().len(), 1);
+    }
+
+    #[test]
+    fn falls_back_to_ipv4_when_ipv6_doesnt_answer() {
+        let (result, servers) = resolve_with_glue_of_both_families(true, false);
+        assert_eq!(result.unwrap(), Ipv4Addr::new(192, 0, 2, 4));
+        assert_eq!(servers[2].queries().len(), 1);
+        assert_eq!(servers[1].queries().len(), 1);
+    }
+
+    #[test]
+    fn type_histogram_counts_every_section() {
+        let header = DNSHeader::from_bytes(&[0; DNS_HEADER_SIZE]).unwrap();
+        let packet = DNSPacket::new(
+            header,
+            vec![],
+            vec![
+                a("example.com", Ipv4Addr::new(192, 0, 2, 1)),
+                a("example.com", Ipv4Addr::new(192, 0, 2, 2)),
+            ],
+            vec![name_rr("example.com", TypeField::NS, "ns1.example.com")],
+            vec![rr(
+                "ns1.example.com",
+                TypeField::AAAA,
+                &Ipv6Addr::LOCALHOST.octets(),
+            )],
+        );
+        assert_eq!(
+            packet.type_histogram(),
+            HashMap::from([(TypeField::A, 2), (TypeField::AAAA, 1), (TypeField::NS, 1),])
+        );
+    }
+
+    #[test]
+    fn cancellation_aborts_resolution() {
+        let cancellation = Arc::new(AtomicBool::new(false));
+        let root = {
+            let cancellation = cancellation.clone();
+            let referral = delegation("example.com", Ipv4Addr::new(127, 0, 0, 2));
+            Box::new(move |query: &DNSPacket| {
+                // cancelled while the first query is in flight
+                cancellation.store(true, Ordering::Relaxed);
+                referral(query)
+            })
+        };
+        let (port, servers) = start_servers(vec![
+            (Ipv4Addr::LOCALHOST.into(), root),
+            (
+                Ipv4Addr::new(127, 0, 0, 2).into(),
+                answer_a(Ipv4Addr::new(192, 0, 2, 1)),
+            ),
+        ]);
+        let resolver = Resolver::new(ResolverConfig {
+            cancellation: Some(cancellation),
+            ..config(port, Ipv4Addr::LOCALHOST)
+        });
+        let error = resolver
+            .resolve(&DomainName::from("example.com"), TypeField::A)
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Interrupted);
+        assert_eq!(servers[0].queries().len(), 1);
+        assert!(servers[1].queries().is_empty());
+    }
+
+    #[test]
+    fn falls_back_to_tcp_after_udp_attempts() {
+        let (port, servers) = start_servers(vec![(Ipv4Addr::LOCALHOST.into(), Box::new(|_| None))]);
+        let listener = std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, port)).unwrap();
+        let tcp_server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let query = tcp::TcpMessageReader::new(&stream)
+                .read_packet()
+                .unwrap()
+                .unwrap();
+            let response = answer(&query, vec![a("example.com", Ipv4Addr::new(192, 0, 2, 1))]);
+            tcp::write_message(&mut &stream, &response.to_bytes()).unwrap();
+        });
+        let resolver = Resolver::new(ResolverConfig {
+            udp_attempts_before_tcp: Some(3),
+            timeout: Duration::from_millis(100),
+            ..config(port, Ipv4Addr::LOCALHOST)
+        });
+
+        let address = resolver
+            .resolve(&DomainName::from("example.com"), TypeField::A)
+            .unwrap();
+        assert_eq!(address, Ipv4Addr::new(192, 0, 2, 1));
+        assert_eq!(servers[0].queries().len(), 3);
+        tcp_server.join().unwrap();
+    }
+
+    #[test]
+    fn to_fqdn_has_trailing_dot() {
+        assert_eq!(DomainName::from("example.com").to_fqdn(), "example.com.");
+        assert_eq!(DomainName::from("example.com.").to_fqdn(), "example.com.");
+        assert_eq!(DomainName::from(".").to_fqdn(), ".");
+        assert_eq!(DomainName::from("example.com").string, "example.com");
+    }
+
+    #[test]
+    fn rejects_compression_chain_longer_than_255_bytes() {
+        // every name is a label of 63 bytes followed by a pointer to the previous one
+        let mut data = vec![0; DNS_HEADER_SIZE];
+        let mut starts = vec![];
+        for i in 0..4 {
+            starts.push(data.len() as u16);
+            data.push(63);
+            data.extend_from_slice(&[b'a'; 63]);
+            match i {
+                0 => data.push(0),
+                _ => data.extend_from_slice(&(0xc000 | starts[i - 1]).to_be_bytes()),
+            }
+        }
+        let read_name = |start: u16| {
+            let mut reader = Cursor::new(&data[..]);
+            reader.set_position(u64::from(start));
+            DomainName::from_reader(&mut reader)
+        };
+
+        // 3 labels take 193 bytes
+        assert_eq!(read_name(starts[2]).unwrap().raw_labels.len(), 3);
+        let error = read_name(starts[3]).unwrap_err();
+        assert_eq!(
+            DnsError::from_io(&error),
+            Some(&DnsError::NameTooLong { length: 257 })
+        );
+    }
+
+    #[test]
+    fn ttl_remaining_after_caching() {
+        let mut record = a("example.com", Ipv4Addr::new(192, 0, 2, 1));
+        record.ttl = 100;
+        let cached_at = Instant::now();
+        assert_eq!(
+            record.ttl_remaining(cached_at, cached_at + Duration::from_secs(30)),
+            70
+        );
+        assert_eq!(
+            record.ttl_remaining(cached_at, cached_at + Duration::from_secs(130)),
+            0
+        );
+    }
+
+    /// Resolves `name` with a single mock root server answering with `handler`.
+    fn resolve_from_root(name: &str, handler: Handler) -> Result<Vec<DNSRecord>, Error> {
+        let (port, _servers) = start_servers(vec![(Ipv4Addr::LOCALHOST.into(), handler)]);
+        let config = ResolverConfig {
+            timeout: Duration::from_millis(200),
+            ..config(port, Ipv4Addr::LOCALHOST)
+        };
+        resolve_records_with_config(&DomainName::from(name), TypeField::A, &config)
+    }
+
+    fn dns_error(result: Result<Vec<DNSRecord>, Error>) -> DnsError {
+        DnsError::from_io(&result.unwrap_err()).unwrap().clone()
+    }
+
+    #[test]
+    fn nxdomain_error() {
+        let result = resolve_from_root(
+            "nope.example.com",
+            Box::new(|query| Some(rcode(query, flags::RCODE_NXDOMAIN))),
+        );
+        let error = dns_error(result);
+        assert_eq!(
+            error,
+            DnsError::NXDomain {
+                name: "nope.example.com".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn nodata_error() {
+        let result =
+            resolve_from_root("example.com", Box::new(|query| Some(answer(query, vec![]))));
+        assert_eq!(
+            dns_error(result),
+            DnsError::NoData {
+                name: "example.com".to_string(),
+                type_field: TypeField::A,
+            }
+        );
+    }
+
+    #[test]
+    fn unreachable_error() {
+        let result = resolve_from_root("example.com", Box::new(|_| None));
+        let error = result.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::HostUnreachable);
+        assert_eq!(
+            DnsError::from_io(&error),
+            Some(&DnsError::Unreachable {
+                name_servers: vec![Ipv4Addr::LOCALHOST.into()]
+            })
+        );
+    }
+
+    #[test]
+    fn cname_loop_error() {
+        let result = resolve_from_root(
+            "a.example.com",
+            Box::new(|query| {
+                let name = &query.questions[0].name.string;
+                let target = if name == "a.example.com" {
+                    "b.example.com"
+                } else {
+                    "a.example.com"
+                };
+                Some(answer(query, vec![name_rr(name, TypeField::CNAME, target)]))
+            }),
+        );
+        assert!(matches!(
+            dns_error(result),
+            DnsError::CnameChainTooLong { .. }
+        ));
+    }
+
+    #[test]
+    fn referral_loop_error() {
+        let queries = Arc::new(AtomicUsize::new(0));
+        let received = queries.clone();
+        let result = resolve_from_root(
+            "example.com",
+            Box::new(move |query| {
+                received.fetch_add(1, Ordering::Relaxed);
+                let glue = vec![a("ns.example.com", Ipv4Addr::LOCALHOST)];
+                Some(referral(query, "example.com", &["ns.example.com"], glue))
+            }),
+        );
+        assert_eq!(
+            dns_error(result),
+            DnsError::DelegationTooDeep {
+                name: "example.com".to_string()
+            }
+        );
+        // the response with one referral too many is the last one
+        assert_eq!(
+            queries.load(Ordering::Relaxed),
+            usize::from(MAX_REFERRALS) + 1
+        );
+    }
+
+    #[test]
+    fn nameserver_loop_error() {
+        // the only name server of the zone is named within it, but has no glue
+        let result = resolve_from_root(
+            "example.com",
+            Box::new(|query| Some(referral(query, "example.com", &["ns.example.com"], vec![]))),
+        );
+        assert!(matches!(
+            dns_error(result),
+            DnsError::DelegationTooDeep { .. }
+        ));
+    }
+
+    fn question(name: &str, type_field: TypeField) -> DNSQuestion {
+        DNSQuestion {
+            name: DomainName::from(name),
+            type_field: type_field.into(),
+            class: ClassField::IN,
+        }
+    }
+
+    #[test]
+    fn questions_match_ignoring_case() {
+        let query = question("www.example.com", TypeField::A);
+        assert!(query.matches(&question("WWW.Example.COM", TypeField::A)));
+        assert!(!query.matches(&question("www.example.com", TypeField::AAAA)));
+        assert!(!query.matches(&question("example.com", TypeField::A)));
+        let chaos = DNSQuestion {
+            class: ClassField::CH,
+            ..question("www.example.com", TypeField::A)
+        };
+        assert!(!query.matches(&chaos));
+    }
+
+    #[test]
+    fn validate_question_rejects_other_type() {
+        let query =
+            DNSPacket::from(&build_query(&DomainName::from("example.com"), TypeField::A)).unwrap();
+        assert!(validate_question(&query, &answer(&query, vec![])).is_ok());
+        let mut response = answer(&query, vec![]);
+        response.questions[0] = question("example.com", TypeField::MX);
+        assert!(validate_question(&query, &response).is_err());
+    }
+
+    #[test]
+    fn from_lenient_warns_about_reserved_flag() {
+        let mut data = message([0, 0, 0, 0], &[]);
+        // the reserved Z bit is 0x40 of the flags, in their second byte
+        data[3] |= 0x40;
+        let (packet, warnings) = DNSPacket::from_lenient(&data).unwrap();
+        assert_eq!(packet.header.flags.z(), 0b100);
+        assert!(packet.header.flags.has_reserved_bit());
+        assert_eq!(warnings, vec![Warning::ReservedFlag { z: 0b100 }]);
+
+        // AD and CD are Z bits too, but no longer reserved
+        let mut data = message([0, 0, 0, 0], &[]);
+        data[3] |= 0x30;
+        let (packet, warnings) = DNSPacket::from_lenient(&data).unwrap();
+        assert_eq!(packet.header.flags.z(), 0b011);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn resolve_many_keeps_order() {
+        let authoritative: Handler = Box::new(|query| {
+            let name = query.questions[0].name.string.clone();
+            let last = match name.as_str() {
+                "a.example.com" => 1,
+                "b.example.com" => 2,
+                "c.example.com" => 3,
+                _ => return Some(rcode(query, flags::RCODE_NXDOMAIN)),
+            };
+            Some(answer(
+                query,
+                vec![a(&name, Ipv4Addr::new(192, 0, 2, last as u8))],
+            ))
+        });
+        let (port, _servers) = start_servers(vec![(Ipv4Addr::LOCALHOST.into(), authoritative)]);
+        let names: Vec<(DomainName, TypeField)> = [
+            "a.example.com",
+            "nope.example.com",
+            "b.example.com",
+            "c.example.com",
+        ]
+        .iter()
+        .map(|x| (DomainName::from(x), TypeField::A))
+        .collect();
+        let results = Resolver::new(config(port, Ipv4Addr::LOCALHOST)).resolve_many(&names);
+
+        let addresses: Vec<Option<Ipv4Addr>> = results.into_iter().map(Result::ok).collect();
+        assert_eq!(
+            addresses,
+            vec![
+                Some(Ipv4Addr::new(192, 0, 2, 1)),
+                None,
+                Some(Ipv4Addr::new(192, 0, 2, 2)),
+                Some(Ipv4Addr::new(192, 0, 2, 3)),
+            ]
+        );
+    }
+
+    #[test]
+    fn error_names_last_server() {
+        let com = Ipv4Addr::new(127, 0, 0, 2);
+        let example = Ipv4Addr::new(127, 0, 0, 3);
+        let (port, _servers) = start_servers(vec![
+            (Ipv4Addr::LOCALHOST.into(), delegation("com", com)),
+            (com.into(), delegation("example.com", example)),
+            (
+                example.into(),
+                Box::new(|query| Some(rcode(query, flags::RCODE_NXDOMAIN))),
+            ),
+        ]);
+        let error = Resolver::new(config(port, Ipv4Addr::LOCALHOST))
+            .resolve(&DomainName::from("nope.example.com"), TypeField::A)
+            .unwrap_err();
+
+        let resolution_error = ResolutionError::from_io(&error).unwrap();
+        assert_eq!(resolution_error.last_server(), Some(example.into()));
+        assert_eq!(
+            resolution_error.path,
+            vec![
+                IpAddr::from(Ipv4Addr::LOCALHOST),
+                com.into(),
+                example.into()
+            ]
+        );
+        assert!(error
+            .to_string()
+            .contains("127.0.0.1 -> 127.0.0.2 -> 127.0.0.3"));
+        assert!(matches!(
+            DnsError::from_io(&error),
+            Some(DnsError::NXDomain { .. })
+        ));
+    }
+
+    #[test]
+    fn a_record_must_be_four_bytes() {
+        let long = message(
+            [0, 1, 0, 0],
+            &record("example.com", 1, &[1, 2, 3, 4, 5, 6, 7, 8]),
+        );
+        assert!(DNSPacket::from(&long).is_err());
+
+        let exact = message([0, 1, 0, 0], &record("example.com", 1, &[1, 2, 3, 4]));
+        let packet = DNSPacket::from(&exact).unwrap();
+        assert_eq!(
+            packet.answers[0].ipv4,
+            Some(vec![Ipv4Addr::new(1, 2, 3, 4)])
+        );
+    }
+
+    #[test]
+    fn canonical_bytes_ignore_case() {
+        let mixed = DomainName::from("Example.COM");
+        let lower = DomainName::from("example.com");
+        assert_eq!(mixed.to_canonical_bytes(), lower.to_canonical_bytes());
+        assert_eq!(
+            lower.to_canonical_bytes(),
+            b"\x07example\x03com\x00".to_vec()
+        );
+    }
+
+    #[test]
+    fn oversized_query_needs_tcp() {
+        let header = DNSHeader {
+            id: 1,
+            flags: DNSFlags::default(),
+            num_questions: 0,
+            num_answers: 0,
+            num_authorities: 0,
+            num_additionals: 0,
+        };
+        let questions: Vec<DNSQuestion> = (0..10)
+            .map(|i| {
+                question(
+                    &format!("{}{}.example.com", "a".repeat(60), i),
+                    TypeField::A,
+                )
+            })
+            .collect();
+        let query = DNSPacket::new(header, questions, vec![], vec![], vec![]).to_bytes();
+        assert!(query.len() > 512);
+
+        let error = check_udp_size(&query).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        assert!(error.to_string().contains("use TCP"));
+
+        let small = build_query(&DomainName::from("example.com"), TypeField::A);
+        assert!(check_udp_size(&small).is_ok());
+    }
+
+    #[test]
+    fn parses_question_only() {
+        let query = build_query(&DomainName::from("example.com"), TypeField::MX);
+        let (header, parsed) = DNSPacket::parse_question_only(&query).unwrap();
+        assert_eq!(header.id, u16::from_be_bytes([query[0], query[1]]));
+        assert_eq!(header.num_questions, 1);
+        assert_eq!(
+            parsed.to_bytes(),
+            question("example.com", TypeField::MX).to_bytes()
+        );
+
+        assert!(DNSPacket::parse_question_only(&message([0, 0, 0, 0], &[])).is_err());
+    }
+
+    #[test]
+    fn builds_update_adding_a_record() {
+        let zone = DomainName::from("example.com");
+        let record = a("www.example.com", Ipv4Addr::new(192, 0, 2, 1));
+        let update = build_update(&zone, &[], std::slice::from_ref(&record));
+
+        let packet = DNSPacket::from(&update).unwrap();
+        assert_eq!(packet.header.flags.opcode(), OpCode::Update);
+        assert!(!packet.header.flags.is_response());
+        assert_eq!(packet.header.num_questions, 1);
+        assert_eq!(packet.header.num_answers, 0);
+        assert_eq!(packet.header.num_authorities, 1);
+        assert_eq!(packet.header.num_additionals, 0);
+        assert_eq!(
+            packet.questions[0].to_bytes(),
+            question("example.com", TypeField::SOA).to_bytes()
+        );
+        assert_eq!(packet.authorities, vec![record]);
+    }
+
+    #[test]
+    fn authoritative_for_queried_name() {
+        let name = DomainName::from("www.example.com");
+        let query = DNSPacket::from(&build_query(&name, TypeField::A)).unwrap();
+        let response = answer(
+            &query,
+            vec![a("www.example.com", Ipv4Addr::new(192, 0, 2, 1))],
+        );
+        assert!(response.is_authoritative_for(&name));
+        assert!(response.is_authoritative_for(&DomainName::from("WWW.Example.com")));
+        assert!(!response.is_authoritative_for(&DomainName::from("example.com")));
+
+        let cached = DNSPacket::response_to(&query, response.answers.clone());
+        assert!(!cached.is_authoritative_for(&name));
+    }
+
+    #[test]
+    fn answer_name_points_to_question() {
+        let question = DNSQuestion {
+            name: DomainName::from("example.com"),
+            type_field: TypeField::A.into(),
+            class: ClassField::IN,
+        };
+        // the owner name is a pointer to the question name, right after the header
+        let mut answer = vec![0xc0, 12];
+        answer.extend_from_slice(&record("example.com", 1, &[1, 2, 3, 4])[13..]);
+        let data = message([1, 1, 0, 0], &[question.to_bytes(), answer].concat());
+
+        let packet = DNSPacket::from(&data).unwrap();
+        assert_eq!(packet.answers[0].name.string, "example.com");
+        assert_eq!(
+            packet.answers[0].ipv4,
+            Some(vec![Ipv4Addr::new(1, 2, 3, 4)])
+        );
+        assert_eq!(packet.get_answer(), Some(&packet.answers[0]));
+    }
+
+    #[test]
+    fn sets_checking_disabled() {
+        let name = DomainName::from("example.com");
+        let options = QueryOptions {
+            checking_disabled: true,
+            ..Default::default()
+        };
+        let query =
+            DNSPacket::from(&build_query_with_options(&name, TypeField::A, &options)).unwrap();
+        assert!(query.header.flags.checking_disabled());
+        // CD is bit 4 of the second flags byte
+        assert_eq!(query.header.flags.bits() & 0x0010, 0x0010);
+
+        let query = DNSPacket::from(&build_query(&name, TypeField::A)).unwrap();
+        assert!(!query.header.flags.checking_disabled());
+    }
+
+    #[test]
+    fn nameserver_names_returns_every_ns() {
+        let query =
+            DNSPacket::from(&build_query(&DomainName::from("example.com"), TypeField::A)).unwrap();
+        let ns = ["ns1.example.net", "ns2.example.net", "ns3.example.net"];
+        let response = referral(&query, "example.com", &ns, vec![]);
+
+        let names: Vec<String> = response
+            .nameserver_names()
+            .into_iter()
+            .map(|x| x.string)
+            .collect();
+        assert_eq!(names, ns);
+        assert_eq!(
+            response.get_nameserver().unwrap().ns_name,
+            Some(DomainName::from("ns1.example.net"))
+        );
+    }
+
+    #[test]
+    fn tries_each_nameserver_without_glue() {
+        let example = Ipv4Addr::new(127, 0, 0, 2);
+        let root: Handler = Box::new(move |query| {
+            let response = match query.questions[0].name.string.as_str() {
+                "example.com" => referral(
+                    query,
+                    "example.com",
+                    &["ns1.example.net", "ns2.example.net", "ns3.example.net"],
+                    vec![],
+                ),
+                "ns3.example.net" => answer(query, vec![a("ns3.example.net", example)]),
+                _ => rcode(query, flags::RCODE_NXDOMAIN),
+            };
+            Some(response)
+        });
+        let (port, servers) = start_servers(vec![
+            (Ipv4Addr::LOCALHOST.into(), root),
+            (example.into(), answer_a(Ipv4Addr::new(192, 0, 2, 1))),
+        ]);
+        let address = Resolver::new(config(port, Ipv4Addr::LOCALHOST))
+            .resolve(&DomainName::from("example.com"), TypeField::A)
+            .unwrap();
+        assert_eq!(address, Ipv4Addr::new(192, 0, 2, 1));
+
+        let asked: Vec<String> = servers[0]
+            .queries()
+            .iter()
+            .map(|x| x.questions[0].name.string.clone())
+            .collect();
+        assert_eq!(
+            asked,
+            [
+                "example.com",
+                "ns1.example.net",
+                "ns2.example.net",
+                "ns3.example.net"
+            ]
+        );
     }
 }
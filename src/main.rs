@@ -1,4 +1,4 @@
-use implement_dns::{resolve, DomainName};
+use implement_dns::{DomainName, Resolver, TypeField};
 
 fn main() -> Result<(), std::io::Error> {
     env_logger::init();
@@ -10,8 +10,9 @@ fn main() -> Result<(), std::io::Error> {
         "www.facebook.com", // CNAME
     ];
 
+    let resolver = Resolver::default();
     for domain in test_domains {
-        let result = resolve(&DomainName::from(domain), implement_dns::TypeField::A);
+        let result = resolver.resolve(&DomainName::from(domain), TypeField::A);
         println!("{:?}", result);
     }
 
@@ -0,0 +1,82 @@
+use std::io::Cursor;
+
+use crate::reader::read_u16;
+use crate::DomainName;
+
+/// A host willing to act as a mail exchange for the owner name.
+/// See https://datatracker.ietf.org/doc/html/rfc1035#section-3.3.9
+#[derive(Debug, Clone, PartialEq)]
+pub struct MxRecord {
+    /// lower values are preferred
+    pub preference: u16,
+    /// the host acting as a mail exchange
+    pub exchange: DomainName,
+}
+impl MxRecord {
+    pub fn from_reader(reader: &mut Cursor<&[u8]>) -> Result<Self, std::io::Error> {
+        Ok(MxRecord {
+            preference: read_u16(reader)?,
+            exchange: DomainName::from_reader(reader)?,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.preference.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&self.exchange.to_bytes());
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{message, record};
+    use crate::{ClassField, DNSPacket, DNSQuestion, DnsError, DomainName, TypeField};
+
+    fn question() -> Vec<u8> {
+        DNSQuestion {
+            name: DomainName::from("example.com"),
+            type_field: TypeField::MX.into(),
+            class: ClassField::IN,
+        }
+        .to_bytes()
+    }
+
+    #[test]
+    fn exchange_pointing_to_question_then_another_record() {
+        // the owner and the exchange both point to the question name, at offset 12
+        let mut mx = vec![0xc0, 12];
+        mx.extend_from_slice(&(TypeField::MX as u16).to_be_bytes());
+        mx.extend_from_slice(&1u16.to_be_bytes());
+        mx.extend_from_slice(&300u32.to_be_bytes());
+        mx.extend_from_slice(&4u16.to_be_bytes());
+        mx.extend_from_slice(&[0, 10, 0xc0, 12]);
+        let a = record("example.com", 1, &[1, 2, 3, 4]);
+        let data = message([1, 2, 0, 0], &[question(), mx, a].concat());
+
+        let packet = DNSPacket::from(&data).unwrap();
+        let mx = packet.answers[0].mx.as_ref().unwrap();
+        assert_eq!(mx.preference, 10);
+        assert_eq!(mx.exchange.string, "example.com");
+        assert_eq!(packet.answers[1].name.string, "example.com");
+        assert_eq!(packet.answers[1].ipv4, Some(vec![[1, 2, 3, 4].into()]));
+    }
+
+    #[test]
+    fn rejects_exchange_outside_of_rdata() {
+        // only the preference is in the rdata, the exchange would be read from the
+        // next record
+        let mx = record("example.com", TypeField::MX as u16, &[0, 10]);
+        let a = record("example.com", 1, &[1, 2, 3, 4]);
+        let data = message([0, 2, 0, 0], &[mx, a].concat());
+
+        let error = DNSPacket::from(&data).unwrap_err();
+        assert!(matches!(
+            DnsError::from_io(&error),
+            Some(DnsError::RdataLength {
+                type_field: TypeField::MX,
+                length: 2,
+                ..
+            })
+        ));
+    }
+}
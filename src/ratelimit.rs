@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{Clock, SystemClock};
+
+/// Spaces out queries to the same name server, so that resolving many names
+/// doesn't get us rate limited or blocked by root and TLD servers.
+///
+/// It can be shared between resolutions running on different threads, each query
+/// reserves the next free slot for its server.
+#[derive(Debug)]
+pub struct RateLimiter {
+    clock: Arc<dyn Clock>,
+    min_interval: Duration,
+    next_slot: Mutex<HashMap<IpAddr, Instant>>,
+}
+impl RateLimiter {
+    pub fn new(min_interval: Duration) -> Self {
+        RateLimiter::with_clock(min_interval, Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(min_interval: Duration, clock: Arc<dyn Clock>) -> Self {
+        RateLimiter {
+            clock,
+            min_interval,
+            next_slot: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns how long to wait before querying `name_server`, and reserves that slot.
+    pub fn reserve(&self, name_server: IpAddr) -> Duration {
+        let now = self.clock.now();
+        let mut next_slot = self.next_slot.lock().unwrap();
+        let slot = match next_slot.get(&name_server) {
+            Some(slot) if *slot > now => *slot,
+            _ => now,
+        };
+        next_slot.insert(name_server, slot + self.min_interval);
+        slot - now
+    }
+
+    /// Blocks until `name_server` can be queried.
+    pub fn wait(&self, name_server: IpAddr) {
+        let delay = self.reserve(name_server);
+        if !delay.is_zero() {
+            log::debug!("Waiting {:?} before querying {}", delay, name_server);
+            thread::sleep(delay);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MockClock;
+
+    const INTERVAL: Duration = Duration::from_millis(100);
+
+    #[test]
+    fn spaces_queries_to_the_same_server() {
+        let clock = Arc::new(MockClock::new());
+        let limiter = RateLimiter::with_clock(INTERVAL, clock.clone());
+        let server = IpAddr::from([192, 0, 2, 1]);
+
+        assert_eq!(limiter.reserve(server), Duration::ZERO);
+        // a second query right away waits for the whole interval, a third one for two
+        assert_eq!(limiter.reserve(server), INTERVAL);
+        assert_eq!(limiter.reserve(server), INTERVAL * 2);
+
+        clock.advance(INTERVAL * 5);
+        assert_eq!(limiter.reserve(server), Duration::ZERO);
+    }
+
+    #[test]
+    fn servers_are_limited_separately() {
+        let clock = Arc::new(MockClock::new());
+        let limiter = RateLimiter::with_clock(INTERVAL, clock.clone());
+        assert_eq!(
+            limiter.reserve(IpAddr::from([192, 0, 2, 1])),
+            Duration::ZERO
+        );
+        assert_eq!(
+            limiter.reserve(IpAddr::from([192, 0, 2, 2])),
+            Duration::ZERO
+        );
+        clock.advance(INTERVAL / 4);
+        assert_eq!(
+            limiter.reserve(IpAddr::from([192, 0, 2, 1])),
+            INTERVAL * 3 / 4
+        );
+    }
+}
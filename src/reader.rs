@@ -0,0 +1,32 @@
+use std::io::{Cursor, Read};
+
+pub fn read_u8(reader: &mut Cursor<&[u8]>) -> Result<u8, std::io::Error> {
+    let mut bytes = [0u8; 1];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes[0])
+}
+
+pub fn read_u16(reader: &mut Cursor<&[u8]>) -> Result<u16, std::io::Error> {
+    let mut bytes = [0u8; 2];
+    reader.read_exact(&mut bytes)?;
+    Ok(u16::from_be_bytes(bytes))
+}
+
+pub fn read_u32(reader: &mut Cursor<&[u8]>) -> Result<u32, std::io::Error> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_be_bytes(bytes))
+}
+
+pub fn read_bytes(reader: &mut Cursor<&[u8]>, length: usize) -> Result<Vec<u8>, std::io::Error> {
+    let mut bytes = vec![0u8; length];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// A length byte followed by at most 255 bytes, see
+/// https://datatracker.ietf.org/doc/html/rfc1035#section-3.3
+pub fn read_character_string(reader: &mut Cursor<&[u8]>) -> Result<Vec<u8>, std::io::Error> {
+    let length = read_u8(reader)?;
+    read_bytes(reader, length as usize)
+}
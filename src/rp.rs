@@ -0,0 +1,63 @@
+use std::io::Cursor;
+
+use crate::DomainName;
+
+/// The person responsible for the owner name.
+/// See https://datatracker.ietf.org/doc/html/rfc1183#section-2.2
+#[derive(Debug, Clone, PartialEq)]
+pub struct RpRecord {
+    /// the mailbox of the responsible person, the first label is the local part
+    pub mbox: DomainName,
+    /// a name with TXT records about the responsible person, the root if there are none
+    pub txt: DomainName,
+}
+impl RpRecord {
+    pub fn from_reader(reader: &mut Cursor<&[u8]>) -> Result<Self, std::io::Error> {
+        Ok(RpRecord {
+            mbox: DomainName::from_reader(reader)?,
+            txt: DomainName::from_reader(reader)?,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.mbox.to_bytes();
+        bytes.extend_from_slice(&self.txt.to_bytes());
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{message, record};
+    use crate::{DNSPacket, TypeField};
+
+    #[test]
+    fn decodes_rp_rdata() {
+        let mut rdata = DomainName::from("admin.example.com").to_bytes();
+        rdata.extend_from_slice(&DomainName::from("info.example.com").to_bytes());
+        let data = message(
+            [0, 1, 0, 0],
+            &record("example.com", TypeField::RP as u16, &rdata),
+        );
+        let packet = DNSPacket::from(&data).unwrap();
+        let rp = packet.answers[0].rp.as_ref().unwrap();
+        assert_eq!(rp.mbox.string, "admin.example.com");
+        assert_eq!(rp.txt.string, "info.example.com");
+        assert_eq!(rp.to_bytes(), rdata);
+    }
+
+    #[test]
+    fn decodes_compressed_names() {
+        // admin + a pointer to the owner name, then the root for no TXT
+        let rdata = [5, b'a', b'd', b'm', b'i', b'n', 0xc0, 12, 0];
+        let data = message(
+            [0, 1, 0, 0],
+            &record("example.com", TypeField::RP as u16, &rdata),
+        );
+        let packet = DNSPacket::from(&data).unwrap();
+        let rp = packet.answers[0].rp.as_ref().unwrap();
+        assert_eq!(rp.mbox.string, "admin.example.com");
+        assert_eq!(rp.txt, DomainName::from("."));
+    }
+}
@@ -0,0 +1,126 @@
+use std::io::{Cursor, Error, ErrorKind};
+
+use crate::reader::{read_bytes, read_u16, read_u32, read_u8};
+use crate::{DomainName, TypeField};
+
+/// The signature of an RRset.
+/// See https://datatracker.ietf.org/doc/html/rfc4034#section-3.1
+#[derive(Debug, Clone, PartialEq)]
+pub struct RrsigRecord {
+    /// the type of the RRset covered by this signature, kept as a number as it can
+    /// be a type we don't parse, e.g. DS, see `covered_type`
+    pub type_covered: u16,
+    /// the cryptographic algorithm used to create the signature
+    pub algorithm: u8,
+    /// the number of labels in the original owner name, without wildcards
+    pub labels: u8,
+    /// the TTL of the covered RRset as it appears in the authoritative zone
+    pub original_ttl: u32,
+    /// the signature is valid until this time, in seconds since the epoch
+    pub sig_expiration: u32,
+    /// the signature is valid from this time, in seconds since the epoch
+    pub sig_inception: u32,
+    /// identifies the DNSKEY record used to validate the signature
+    pub key_tag: u16,
+    /// the owner of the DNSKEY record
+    pub signer_name: DomainName,
+    pub signature: Vec<u8>,
+}
+impl RrsigRecord {
+    /// Reads the rdata starting at the current position of the reader, `data_len`
+    /// bytes long.
+    pub fn from_reader(reader: &mut Cursor<&[u8]>, data_len: u16) -> Result<Self, std::io::Error> {
+        let end = reader.position() + u64::from(data_len);
+        let type_covered = read_u16(reader)?;
+        let algorithm = read_u8(reader)?;
+        let labels = read_u8(reader)?;
+        let original_ttl = read_u32(reader)?;
+        let sig_expiration = read_u32(reader)?;
+        let sig_inception = read_u32(reader)?;
+        let key_tag = read_u16(reader)?;
+        // the signer name must not be compressed, but we are lenient about it
+        let signer_name = DomainName::from_reader(reader)?;
+
+        let signature_len = end.checked_sub(reader.position()).ok_or_else(|| {
+            Error::new(ErrorKind::InvalidData, "RRSIG signer name overflows rdata")
+        })?;
+        let signature = read_bytes(reader, signature_len as usize)?;
+
+        Ok(RrsigRecord {
+            type_covered,
+            algorithm,
+            labels,
+            original_ttl,
+            sig_expiration,
+            sig_inception,
+            key_tag,
+            signer_name,
+            signature,
+        })
+    }
+
+    /// The type of the RRset covered by this signature, if it is one we know about.
+    pub fn covered_type(&self) -> Option<TypeField> {
+        TypeField::from_bytes(&self.type_covered.to_be_bytes()).ok()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.type_covered.to_be_bytes().to_vec();
+        bytes.push(self.algorithm);
+        bytes.push(self.labels);
+        bytes.extend_from_slice(&self.original_ttl.to_be_bytes());
+        bytes.extend_from_slice(&self.sig_expiration.to_be_bytes());
+        bytes.extend_from_slice(&self.sig_inception.to_be_bytes());
+        bytes.extend_from_slice(&self.key_tag.to_be_bytes());
+        bytes.extend_from_slice(&self.signer_name.to_bytes());
+        bytes.extend_from_slice(&self.signature);
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{message, record};
+    use crate::DNSPacket;
+
+    fn rdata(type_covered: u16) -> Vec<u8> {
+        let mut bytes = type_covered.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&[13, 2]);
+        bytes.extend_from_slice(&3600u32.to_be_bytes());
+        bytes.extend_from_slice(&1_700_086_400u32.to_be_bytes());
+        bytes.extend_from_slice(&1_700_000_000u32.to_be_bytes());
+        bytes.extend_from_slice(&12345u16.to_be_bytes());
+        bytes.extend_from_slice(&DomainName::from("example.com").to_bytes());
+        bytes.extend_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+        bytes
+    }
+
+    #[test]
+    fn decodes_rrsig_rdata() {
+        let bytes = rdata(1);
+        let rrsig =
+            RrsigRecord::from_reader(&mut Cursor::new(&bytes[..]), bytes.len() as u16).unwrap();
+        assert_eq!(rrsig.type_covered, 1);
+        assert_eq!(rrsig.covered_type(), Some(TypeField::A));
+        assert_eq!(rrsig.algorithm, 13);
+        assert_eq!(rrsig.labels, 2);
+        assert_eq!(rrsig.original_ttl, 3600);
+        assert_eq!(rrsig.sig_expiration, 1_700_086_400);
+        assert_eq!(rrsig.sig_inception, 1_700_000_000);
+        assert_eq!(rrsig.key_tag, 12345);
+        assert_eq!(rrsig.signer_name.string, "example.com");
+        assert_eq!(rrsig.signature, vec![0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(rrsig.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn parses_signature_of_unknown_type() {
+        // DS, which we don't parse
+        let answer = record("example.com", TypeField::RRSIG as u16, &rdata(43));
+        let packet = DNSPacket::from(&message([0, 1, 0, 0], &answer)).unwrap();
+        let rrsig = packet.answers[0].rrsig.as_ref().unwrap();
+        assert_eq!(rrsig.type_covered, 43);
+        assert_eq!(rrsig.covered_type(), None);
+    }
+}
@@ -0,0 +1,29 @@
+use std::fmt::Debug;
+use std::net::IpAddr;
+
+/// Decides in which order the name servers of a delegation are queried, e.g. to
+/// prefer those that answered fastest so far.
+pub trait NameserverSelector: Debug + Send + Sync {
+    /// Returns the addresses to query, in order, from those of the delegation.
+    fn order(&self, candidates: Vec<IpAddr>) -> Vec<IpAddr>;
+}
+
+/// Queries the name servers in the order they appear in the response.
+#[derive(Debug, Default)]
+pub struct ParseOrder;
+impl NameserverSelector for ParseOrder {
+    fn order(&self, candidates: Vec<IpAddr>) -> Vec<IpAddr> {
+        candidates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_order_keeps_candidates() {
+        let candidates: Vec<IpAddr> = vec![[192, 0, 2, 2].into(), [192, 0, 2, 1].into()];
+        assert_eq!(ParseOrder.order(candidates.clone()), candidates);
+    }
+}
@@ -0,0 +1,100 @@
+use std::io::Cursor;
+
+use crate::reader::read_u32;
+use crate::DomainName;
+
+/// Marks the start of a zone of authority, also sent along negative answers so
+/// they can be cached.
+/// See https://datatracker.ietf.org/doc/html/rfc1035#section-3.3.13
+#[derive(Debug, Clone, PartialEq)]
+pub struct SoaRecord {
+    /// the name server that was the original or primary source of data for this zone
+    pub mname: DomainName,
+    /// the mailbox of the person responsible for this zone
+    pub rname: DomainName,
+    /// the version number of the original copy of the zone
+    pub serial: u32,
+    /// seconds before the zone should be refreshed
+    pub refresh: u32,
+    /// seconds before a failed refresh should be retried
+    pub retry: u32,
+    /// seconds after which the zone is no longer authoritative
+    pub expire: u32,
+    /// the TTL of negative answers, see https://datatracker.ietf.org/doc/html/rfc2308
+    pub minimum: u32,
+}
+impl SoaRecord {
+    pub fn from_reader(reader: &mut Cursor<&[u8]>) -> Result<Self, std::io::Error> {
+        Ok(SoaRecord {
+            mname: DomainName::from_reader(reader)?,
+            rname: DomainName::from_reader(reader)?,
+            serial: read_u32(reader)?,
+            refresh: read_u32(reader)?,
+            retry: read_u32(reader)?,
+            expire: read_u32(reader)?,
+            minimum: read_u32(reader)?,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.mname.to_bytes();
+        bytes.extend_from_slice(&self.rname.to_bytes());
+        bytes.extend_from_slice(&self.serial.to_be_bytes());
+        bytes.extend_from_slice(&self.refresh.to_be_bytes());
+        bytes.extend_from_slice(&self.retry.to_be_bytes());
+        bytes.extend_from_slice(&self.expire.to_be_bytes());
+        bytes.extend_from_slice(&self.minimum.to_be_bytes());
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{message, name_rr, record, rr};
+    use crate::{flags, DNSPacket, TypeField};
+
+    fn soa() -> SoaRecord {
+        SoaRecord {
+            mname: DomainName::from("ns1.example.com"),
+            rname: DomainName::from("hostmaster.example.com"),
+            serial: 2024010101,
+            refresh: 7200,
+            retry: 3600,
+            expire: 1209600,
+            minimum: 300,
+        }
+    }
+
+    #[test]
+    fn soa_round_trip() {
+        let bytes = soa().to_bytes();
+        let data = message([0, 1, 0, 0], &record("example.com", 6, &bytes));
+        let packet = DNSPacket::from(&data).unwrap();
+        assert_eq!(packet.answers[0].soa, Some(soa()));
+    }
+
+    #[test]
+    fn negative_soa_of_nxdomain_response() {
+        let query = DNSPacket::from(&crate::build_query(
+            &DomainName::from("nope.example.com"),
+            TypeField::A,
+        ))
+        .unwrap();
+        let mut response = DNSPacket::response_to(&query, vec![]);
+        response.header.flags = response.header.flags.with_rcode(flags::RCODE_NXDOMAIN);
+        let response = DNSPacket::new(
+            response.header,
+            response.questions,
+            vec![],
+            vec![
+                name_rr("example.com", TypeField::NS, "ns1.example.com"),
+                rr("example.com", TypeField::SOA, &soa().to_bytes()),
+            ],
+            vec![],
+        );
+        let response = DNSPacket::from(&response.to_bytes()).unwrap();
+        assert_eq!(response.negative_soa(), Some(&soa()));
+        assert_eq!(query.negative_soa(), None);
+    }
+}
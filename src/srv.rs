@@ -0,0 +1,35 @@
+use std::io::Cursor;
+
+use crate::reader::read_u16;
+use crate::DomainName;
+
+/// The location of a service, the owner name looks like `_service._proto.name`.
+/// See https://datatracker.ietf.org/doc/html/rfc2782
+#[derive(Debug, Clone, PartialEq)]
+pub struct SrvRecord {
+    /// clients must contact the target with the lowest priority they can reach
+    pub priority: u16,
+    /// relative weight of targets with the same priority
+    pub weight: u16,
+    pub port: u16,
+    /// the host providing the service, the root means it isn't available
+    pub target: DomainName,
+}
+impl SrvRecord {
+    pub fn from_reader(reader: &mut Cursor<&[u8]>) -> Result<Self, std::io::Error> {
+        Ok(SrvRecord {
+            priority: read_u16(reader)?,
+            weight: read_u16(reader)?,
+            port: read_u16(reader)?,
+            target: DomainName::from_reader(reader)?,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.priority.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&self.weight.to_be_bytes());
+        bytes.extend_from_slice(&self.port.to_be_bytes());
+        bytes.extend_from_slice(&self.target.to_bytes());
+        bytes
+    }
+}
@@ -0,0 +1,78 @@
+use std::io::{Cursor, Error, ErrorKind};
+
+use crate::reader::{read_bytes, read_u8};
+
+/// Algorithm number of Ed25519 host keys.
+/// See https://datatracker.ietf.org/doc/html/rfc7479#section-3
+pub const ALGORITHM_ED25519: u8 = 4;
+
+/// Fingerprint type of SHA-256 hashes.
+/// See https://datatracker.ietf.org/doc/html/rfc6594#section-4.1
+pub const FP_TYPE_SHA256: u8 = 2;
+
+/// The fingerprint of an SSH host key of the owner name, to check it without
+/// trusting it on first use.
+/// See https://datatracker.ietf.org/doc/html/rfc4255#section-3.1
+#[derive(Debug, Clone, PartialEq)]
+pub struct SshfpRecord {
+    /// the algorithm of the public key, e.g. 4 for Ed25519
+    pub algorithm: u8,
+    /// how the fingerprint is computed, 1 for SHA-1 and 2 for SHA-256
+    pub fp_type: u8,
+    pub fingerprint: Vec<u8>,
+}
+impl SshfpRecord {
+    /// Reads the rdata starting at the current position of the reader, `data_len`
+    /// bytes long.
+    pub fn from_reader(reader: &mut Cursor<&[u8]>, data_len: u16) -> Result<Self, std::io::Error> {
+        let fingerprint_len = data_len
+            .checked_sub(2)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "SSHFP rdata too short"))?;
+        Ok(SshfpRecord {
+            algorithm: read_u8(reader)?,
+            fp_type: read_u8(reader)?,
+            fingerprint: read_bytes(reader, fingerprint_len as usize)?,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![self.algorithm, self.fp_type];
+        bytes.extend_from_slice(&self.fingerprint);
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{message, record};
+    use crate::{DNSPacket, TypeField};
+
+    #[test]
+    fn decodes_ed25519_sha256_fingerprint() {
+        let fingerprint: Vec<u8> = (0..32).collect();
+        let rdata = [&[ALGORITHM_ED25519, FP_TYPE_SHA256][..], &fingerprint].concat();
+        let data = message(
+            [0, 1, 0, 0],
+            &record("host.example.com", TypeField::SSHFP as u16, &rdata),
+        );
+        let packet = DNSPacket::from(&data).unwrap();
+        let sshfp = packet.answers[0].sshfp.as_ref().unwrap();
+        assert_eq!(
+            *sshfp,
+            SshfpRecord {
+                algorithm: ALGORITHM_ED25519,
+                fp_type: FP_TYPE_SHA256,
+                fingerprint,
+            }
+        );
+        assert_eq!(sshfp.to_bytes(), rdata);
+    }
+
+    #[test]
+    fn rejects_short_rdata() {
+        let rdata = [ALGORITHM_ED25519];
+        let mut reader = Cursor::new(&rdata[..]);
+        assert!(SshfpRecord::from_reader(&mut reader, rdata.len() as u16).is_err());
+    }
+}
@@ -0,0 +1,108 @@
+use std::io::{Cursor, Error, ErrorKind};
+
+use crate::reader::{read_bytes, read_u16};
+use crate::DomainName;
+
+/// SvcParamKey of the list of supported ALPN protocol identifiers, e.g. `h2` or `h3`
+pub const ALPN_KEY: u16 = 1;
+
+/// Service binding records, HTTPS records use the same format.
+/// See https://datatracker.ietf.org/doc/html/rfc9460#section-2.2
+#[derive(Debug, Clone, PartialEq)]
+pub struct SvcbRecord {
+    /// 0 for alias mode, otherwise the priority of this endpoint (lower is preferred)
+    pub priority: u16,
+    /// the alias target or the alternative endpoint, the root means the owner name
+    pub target: DomainName,
+    /// SvcParams as (key, value) pairs
+    pub params: Vec<(u16, Vec<u8>)>,
+}
+impl SvcbRecord {
+    /// Reads the rdata starting at the current position of the reader, `data_len`
+    /// bytes long.
+    pub fn from_reader(reader: &mut Cursor<&[u8]>, data_len: u16) -> Result<Self, std::io::Error> {
+        let end = reader.position() + u64::from(data_len);
+        let priority = read_u16(reader)?;
+        let target = DomainName::from_reader(reader)?;
+
+        let mut params = vec![];
+        while reader.position() < end {
+            let key = read_u16(reader)?;
+            let length = read_u16(reader)?;
+            if reader.position() + u64::from(length) > end {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "SvcParam overflows rdata",
+                ));
+            }
+            params.push((key, read_bytes(reader, length as usize)?));
+        }
+
+        Ok(SvcbRecord {
+            priority,
+            target,
+            params,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.priority.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&self.target.to_bytes());
+        for (key, value) in &self.params {
+            bytes.extend_from_slice(&key.to_be_bytes());
+            bytes.extend_from_slice(&(value.len() as u16).to_be_bytes());
+            bytes.extend_from_slice(value);
+        }
+        bytes
+    }
+
+    /// The ALPN protocol identifiers advertised by the endpoint.
+    pub fn alpn(&self) -> Vec<String> {
+        let Some((_, value)) = self.params.iter().find(|(key, _)| *key == ALPN_KEY) else {
+            return vec![];
+        };
+        let mut protocols = vec![];
+        let mut rest = value.as_slice();
+        while let Some((&length, tail)) = rest.split_first() {
+            let length = (length as usize).min(tail.len());
+            protocols.push(String::from_utf8_lossy(&tail[..length]).into_owned());
+            rest = &tail[length..];
+        }
+        protocols
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{message, record};
+    use crate::{DNSPacket, TypeField};
+
+    #[test]
+    fn decodes_https_record_with_alpn() {
+        let mut rdata = 1u16.to_be_bytes().to_vec();
+        rdata.push(0); // the owner name is the target
+        rdata.extend_from_slice(&ALPN_KEY.to_be_bytes());
+        rdata.extend_from_slice(&6u16.to_be_bytes());
+        rdata.extend_from_slice(b"\x02h2\x02h3");
+        let data = message(
+            [0, 1, 0, 0],
+            &record("example.com", TypeField::HTTPS as u16, &rdata),
+        );
+
+        let packet = DNSPacket::from(&data).unwrap();
+        let svcb = packet.answers[0].svcb.as_ref().unwrap();
+        assert_eq!(svcb.priority, 1);
+        assert_eq!(svcb.target, DomainName::from("."));
+        assert_eq!(svcb.params, vec![(ALPN_KEY, b"\x02h2\x02h3".to_vec())]);
+        assert_eq!(svcb.alpn(), vec!["h2", "h3"]);
+        assert_eq!(svcb.to_bytes(), rdata);
+    }
+
+    #[test]
+    fn rejects_param_overflowing_rdata() {
+        let rdata = [0, 1, 0, 0, 1, 0, 10, b'h'];
+        let mut reader = Cursor::new(&rdata[..]);
+        assert!(SvcbRecord::from_reader(&mut reader, rdata.len() as u16).is_err());
+    }
+}
@@ -0,0 +1,77 @@
+use std::net::{IpAddr, Ipv4Addr};
+
+use crate::{DNSPacket, DomainName, TypeField, UdpConnection, DEFAULT_DNS_PORT, QUERY_TIMEOUT};
+
+const RESOLV_CONF: &str = "/etc/resolv.conf";
+
+/// The name server used when resolv.conf doesn't list any, as the C library does.
+const DEFAULT_NAME_SERVER: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+/// The addresses of the `nameserver` lines of a resolv.conf, IPv4 or IPv6, in order.
+/// See resolv.conf(5)
+pub fn parse_resolv_conf(content: &str) -> Vec<IpAddr> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("nameserver") => {
+                    let address = words.next()?;
+                    let parsed = address.parse().ok();
+                    if parsed.is_none() {
+                        // e.g. link-local IPv6 addresses with a %interface suffix
+                        log::warn!("Skipping name server {} of resolv.conf", address);
+                    }
+                    parsed
+                }
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Asks the first name server of /etc/resolv.conf to resolve the name recursively,
+/// like most applications do through the C library.
+pub fn resolve_system(
+    domain_name: &DomainName,
+    type_field: TypeField,
+) -> Result<DNSPacket, std::io::Error> {
+    let name_server = match std::fs::read_to_string(RESOLV_CONF) {
+        Ok(content) => parse_resolv_conf(&content).first().copied(),
+        Err(error) => {
+            log::warn!("Can't read {}: {}", RESOLV_CONF, error);
+            None
+        }
+    };
+    let name_server = name_server.unwrap_or(DEFAULT_NAME_SERVER);
+    UdpConnection::connect(name_server, DEFAULT_DNS_PORT, QUERY_TIMEOUT)?
+        .resolve(domain_name, type_field)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv6Addr;
+
+    use super::*;
+
+    #[test]
+    fn parses_sample_resolv_conf() {
+        let content = "\
+# generated by NetworkManager
+search example.com
+nameserver 192.0.2.53
+nameserver   198.51.100.53
+nameserver 2001:db8::53
+options edns0 trust-ad
+";
+        assert_eq!(
+            parse_resolv_conf(content),
+            vec![
+                IpAddr::V4(Ipv4Addr::new(192, 0, 2, 53)),
+                IpAddr::V4(Ipv4Addr::new(198, 51, 100, 53)),
+                IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 0x53)),
+            ]
+        );
+        assert!(parse_resolv_conf("search example.com\n").is_empty());
+    }
+}
@@ -0,0 +1,224 @@
+use std::io::{Error, ErrorKind, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::time::Duration;
+
+use socket2::{Domain, Protocol, Socket, Type};
+
+use crate::DNSPacket;
+
+/// Over TCP every message is preceded by its length in two bytes.
+/// See https://datatracker.ietf.org/doc/html/rfc1035#section-4.2.2
+const LENGTH_PREFIX_SIZE: usize = 2;
+
+/// Reads the messages of a TCP stream one at a time, buffering partial reads until
+/// a whole message has arrived. Zone transfers are made of many messages, so they
+/// don't have to be held in memory all at once.
+#[derive(Debug)]
+pub struct TcpMessageReader<R> {
+    inner: R,
+    buffer: Vec<u8>,
+}
+impl<R: Read> TcpMessageReader<R> {
+    pub fn new(inner: R) -> Self {
+        TcpMessageReader {
+            inner,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// The length of the message at the start of the buffer, with its prefix, once
+    /// all of it has been read.
+    fn complete_message_len(&self) -> Option<usize> {
+        let prefix = self.buffer.get(..LENGTH_PREFIX_SIZE)?;
+        let length = LENGTH_PREFIX_SIZE + u16::from_be_bytes([prefix[0], prefix[1]]) as usize;
+        (self.buffer.len() >= length).then_some(length)
+    }
+
+    /// Returns the next message, or `None` if the stream was closed after the
+    /// previous one.
+    pub fn read_packet(&mut self) -> Result<Option<DNSPacket>, std::io::Error> {
+        let mut chunk = [0u8; 4096];
+        loop {
+            if let Some(length) = self.complete_message_len() {
+                let packet = DNSPacket::from(&self.buffer[LENGTH_PREFIX_SIZE..length]);
+                self.buffer.drain(..length);
+                return packet.map(Some);
+            }
+            let read = match self.inner.read(&mut chunk) {
+                Ok(read) => read,
+                Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            };
+            if read == 0 {
+                if self.buffer.is_empty() {
+                    return Ok(None);
+                }
+                return Err(Error::new(
+                    ErrorKind::UnexpectedEof,
+                    "Stream closed in the middle of a message",
+                ));
+            }
+            self.buffer.extend_from_slice(&chunk[..read]);
+        }
+    }
+}
+impl<R: Read> Iterator for TcpMessageReader<R> {
+    type Item = Result<DNSPacket, std::io::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_packet().transpose()
+    }
+}
+
+/// Writes `message` preceded by its length.
+pub fn write_message(writer: &mut impl Write, message: &[u8]) -> Result<(), std::io::Error> {
+    let length = u16::try_from(message.len())
+        .map_err(|_| Error::new(ErrorKind::InvalidInput, "Message too long for TCP"))?;
+    let mut bytes = length.to_be_bytes().to_vec();
+    bytes.extend_from_slice(message);
+    writer.write_all(&bytes)
+}
+
+/// Sends `message` over a new TCP connection and waits for the response with the
+/// same id, e.g. when UDP is unreliable or the response didn't fit in a datagram.
+/// The connection is made from `source` when given, any local address otherwise.
+pub fn query(
+    source: Option<IpAddr>,
+    server: IpAddr,
+    port: u16,
+    timeout: Duration,
+    message: &[u8],
+) -> Result<DNSPacket, std::io::Error> {
+    let id = message
+        .get(..2)
+        .map(|x| u16::from_be_bytes([x[0], x[1]]))
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "Query is missing its header"))?;
+    let address = SocketAddr::new(server, port);
+    let mut stream = match source {
+        Some(source) => connect_from(source, address, timeout)?,
+        None => TcpStream::connect_timeout(&address, timeout)?,
+    };
+    stream.set_read_timeout(Some(timeout))?;
+    write_message(&mut stream, message)?;
+    let packet = TcpMessageReader::new(stream)
+        .read_packet()?
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::UnexpectedEof,
+                "Connection closed before the response",
+            )
+        })?;
+    if packet.header.id != id {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "Response id doesn't match the query",
+        ));
+    }
+    Ok(packet)
+}
+
+/// Binds the socket to `source` before connecting, which `TcpStream` can't do.
+fn connect_from(
+    source: IpAddr,
+    address: SocketAddr,
+    timeout: Duration,
+) -> Result<TcpStream, std::io::Error> {
+    let socket = Socket::new(
+        Domain::for_address(address),
+        Type::STREAM,
+        Some(Protocol::TCP),
+    )?;
+    socket.bind(&SocketAddr::new(source, 0).into())?;
+    socket.connect_timeout(&address.into(), timeout)?;
+    Ok(socket.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::net::{Ipv4Addr, TcpListener};
+    use std::thread;
+
+    use super::*;
+    use crate::testutil::{message, record};
+    use crate::{build_query, DomainName, TypeField};
+
+    /// Returns one chunk per call to `read`.
+    struct Chunks(VecDeque<Vec<u8>>);
+    impl Read for Chunks {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let Some(chunk) = self.0.pop_front() else {
+                return Ok(0);
+            };
+            buf[..chunk.len()].copy_from_slice(&chunk);
+            Ok(chunk.len())
+        }
+    }
+
+    fn framed(message: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![];
+        write_message(&mut bytes, message).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn reads_message_split_across_three_reads() {
+        let data = message([0, 1, 0, 0], &record("example.com", 1, &[1, 2, 3, 4]));
+        let bytes = framed(&data);
+        // the length prefix is split too
+        let chunks = [&bytes[..1], &bytes[1..20], &bytes[20..]];
+        let mut reader = TcpMessageReader::new(Chunks(chunks.map(<[u8]>::to_vec).into()));
+
+        let packet = reader.read_packet().unwrap().unwrap();
+        assert_eq!(packet.answers[0].name.string, "example.com");
+        assert!(reader.read_packet().unwrap().is_none());
+    }
+
+    #[test]
+    fn reads_messages_sharing_a_read() {
+        let first = message([0, 0, 0, 0], &[]);
+        let second = message([0, 1, 0, 0], &record("example.com", 1, &[1, 2, 3, 4]));
+        let bytes = [framed(&first), framed(&second)].concat();
+        let reader = TcpMessageReader::new(Chunks(VecDeque::from([bytes])));
+        let packets: Vec<DNSPacket> = reader.map(Result::unwrap).collect();
+        assert_eq!(packets.len(), 2);
+        assert_eq!(packets[1].answers.len(), 1);
+    }
+
+    #[test]
+    fn fails_when_stream_closes_mid_message() {
+        let bytes = framed(&message([0, 0, 0, 0], &[]));
+        let mut reader = TcpMessageReader::new(Chunks(VecDeque::from([bytes[..5].to_vec()])));
+        let error = reader.read_packet().unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn connects_from_source_address() {
+        let source = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 5));
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (stream, client) = listener.accept().unwrap();
+            let query = TcpMessageReader::new(&stream)
+                .read_packet()
+                .unwrap()
+                .unwrap();
+            let response = DNSPacket::response_to(&query, vec![]);
+            write_message(&mut &stream, &response.to_bytes()).unwrap();
+            client
+        });
+
+        let query = build_query(&DomainName::from("example.com"), TypeField::A);
+        let timeout = Duration::from_secs(1);
+        let response = super::query(
+            Some(source),
+            Ipv4Addr::LOCALHOST.into(),
+            port,
+            timeout,
+            &query,
+        );
+        assert_eq!(response.unwrap().questions[0].name.string, "example.com");
+        assert_eq!(server.join().unwrap().ip(), source);
+    }
+}
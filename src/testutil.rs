@@ -0,0 +1,185 @@
+//! Helpers to build raw messages and to run mock name servers for the tests.
+
+use std::io::ErrorKind;
+use std::net::{IpAddr, Ipv4Addr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::{
+    ClassField, DNSFlags, DNSHeader, DNSPacket, DNSRecord, DomainName, ResolverConfig, TypeField,
+};
+
+/// A record of class IN with a TTL of 60, `type_value` can be a type we don't know.
+pub fn record(name: &str, type_value: u16, rdata: &[u8]) -> Vec<u8> {
+    let mut bytes = DomainName::from(name).to_bytes();
+    bytes.extend_from_slice(&type_value.to_be_bytes());
+    bytes.extend_from_slice(&1u16.to_be_bytes());
+    bytes.extend_from_slice(&60u32.to_be_bytes());
+    bytes.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+    bytes.extend_from_slice(rdata);
+    bytes
+}
+
+/// A response with id 0x1234 whose sections hold the given number of entries,
+/// `body` being the questions and records that follow the header.
+pub fn message(counts: [u16; 4], body: &[u8]) -> Vec<u8> {
+    let header = DNSHeader {
+        id: 0x1234,
+        flags: DNSFlags::default().with_response(true),
+        num_questions: counts[0],
+        num_answers: counts[1],
+        num_authorities: counts[2],
+        num_additionals: counts[3],
+    };
+    let mut bytes = header.to_bytes();
+    bytes.extend_from_slice(body);
+    bytes
+}
+
+/// A record of class IN with a TTL of 60.
+pub fn rr(name: &str, type_field: TypeField, rdata: &[u8]) -> DNSRecord {
+    DNSRecord::new(
+        DomainName::from(name),
+        type_field,
+        ClassField::IN,
+        60,
+        rdata,
+    )
+    .unwrap()
+}
+
+pub fn a(name: &str, address: Ipv4Addr) -> DNSRecord {
+    rr(name, TypeField::A, &address.octets())
+}
+
+/// A record whose rdata is a single uncompressed name, e.g. NS or CNAME.
+pub fn name_rr(name: &str, type_field: TypeField, target: &str) -> DNSRecord {
+    rr(name, type_field, &DomainName::from(target).to_bytes())
+}
+
+/// The response of an authoritative server to `query`.
+pub fn answer(query: &DNSPacket, answers: Vec<DNSRecord>) -> DNSPacket {
+    let mut response = DNSPacket::response_to(query, answers);
+    response.header.flags = response.header.flags.with_authoritative(true);
+    response
+}
+
+/// A non-authoritative response delegating `zone` to the name servers `ns`, along
+/// with the glue in `additionals`.
+pub fn referral(
+    query: &DNSPacket,
+    zone: &str,
+    ns: &[&str],
+    additionals: Vec<DNSRecord>,
+) -> DNSPacket {
+    let response = DNSPacket::response_to(query, vec![]);
+    let authorities = ns.iter().map(|x| name_rr(zone, TypeField::NS, x)).collect();
+    DNSPacket::new(
+        response.header,
+        response.questions,
+        vec![],
+        authorities,
+        additionals,
+    )
+}
+
+/// An authoritative response with the given RCODE and no records.
+pub fn rcode(query: &DNSPacket, rcode: u8) -> DNSPacket {
+    let mut response = answer(query, vec![]);
+    response.header.flags = response.header.flags.with_rcode(rcode);
+    response
+}
+
+/// Answers the queries it receives with a response, or doesn't answer if `None`.
+pub type Handler = Box<dyn Fn(&DNSPacket) -> Option<DNSPacket> + Send>;
+
+/// A name server answering over UDP on a loopback address until it is dropped.
+pub struct MockServer {
+    queries: Arc<Mutex<Vec<Vec<u8>>>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+impl MockServer {
+    /// The queries received so far, in order.
+    pub fn queries(&self) -> Vec<DNSPacket> {
+        let queries = self.queries.lock().unwrap();
+        queries
+            .iter()
+            .map(|x| DNSPacket::from(x).unwrap())
+            .collect()
+    }
+
+    fn spawn(socket: UdpSocket, handler: Handler) -> Self {
+        let queries = Arc::new(Mutex::new(vec![]));
+        let stop = Arc::new(AtomicBool::new(false));
+        socket
+            .set_read_timeout(Some(Duration::from_millis(20)))
+            .unwrap();
+        let thread = {
+            let queries = queries.clone();
+            let stop = stop.clone();
+            thread::spawn(move || {
+                let mut buf = [0; 4096];
+                while !stop.load(Ordering::Relaxed) {
+                    let Ok((len, src)) = socket.recv_from(&mut buf) else {
+                        continue;
+                    };
+                    queries.lock().unwrap().push(buf[..len].to_vec());
+                    let query = DNSPacket::from(&buf[..len]).unwrap();
+                    if let Some(response) = handler(&query) {
+                        socket.send_to(&response.to_bytes(), src).unwrap();
+                    }
+                }
+            })
+        };
+        MockServer {
+            queries,
+            stop,
+            thread: Some(thread),
+        }
+    }
+}
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Starts a mock server on each address, all on the same free port, which is
+/// returned with them.
+pub fn start_servers(servers: Vec<(IpAddr, Handler)>) -> (u16, Vec<MockServer>) {
+    let (port, sockets) = 'bind: loop {
+        let first = UdpSocket::bind((servers[0].0, 0)).unwrap();
+        let port = first.local_addr().unwrap().port();
+        let mut sockets = vec![first];
+        for (address, _) in &servers[1..] {
+            match UdpSocket::bind((*address, port)) {
+                Ok(socket) => sockets.push(socket),
+                Err(error) if error.kind() == ErrorKind::AddrInUse => continue 'bind,
+                Err(error) => panic!("Can't bind {}: {}", address, error),
+            }
+        }
+        break (port, sockets);
+    };
+    let servers = sockets
+        .into_iter()
+        .zip(servers)
+        .map(|(socket, (_, handler))| MockServer::spawn(socket, handler))
+        .collect();
+    (port, servers)
+}
+
+/// A config resolving from the root server `root` on `port`, with a short timeout.
+pub fn config(port: u16, root: Ipv4Addr) -> ResolverConfig {
+    ResolverConfig {
+        port,
+        root_hints: vec![root],
+        timeout: Duration::from_millis(500),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,76 @@
+use std::io::{Cursor, Error, ErrorKind};
+
+use crate::reader::{read_bytes, read_u8};
+
+/// Associates a TLS server certificate or public key with the name where the
+/// record is found, e.g. `_443._tcp.example.com`.
+/// See https://datatracker.ietf.org/doc/html/rfc6698#section-2.1
+#[derive(Debug, Clone, PartialEq)]
+pub struct TlsaRecord {
+    /// how the association is checked, e.g. 3 for the end entity certificate alone
+    pub usage: u8,
+    /// 0 to match the full certificate, 1 for just its public key
+    pub selector: u8,
+    /// 0 for exact match, 1 and 2 for SHA-256 and SHA-512 hashes
+    pub matching_type: u8,
+    pub cert_association: Vec<u8>,
+}
+impl TlsaRecord {
+    /// Reads the rdata starting at the current position of the reader, `data_len`
+    /// bytes long.
+    pub fn from_reader(reader: &mut Cursor<&[u8]>, data_len: u16) -> Result<Self, std::io::Error> {
+        let association_len = data_len
+            .checked_sub(3)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "TLSA rdata too short"))?;
+        Ok(TlsaRecord {
+            usage: read_u8(reader)?,
+            selector: read_u8(reader)?,
+            matching_type: read_u8(reader)?,
+            cert_association: read_bytes(reader, association_len as usize)?,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![self.usage, self.selector, self.matching_type];
+        bytes.extend_from_slice(&self.cert_association);
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{message, record};
+    use crate::{DNSPacket, TypeField};
+
+    #[test]
+    fn decodes_tlsa_record() {
+        // DANE-EE, SubjectPublicKeyInfo, SHA-256
+        let digest = [0xab; 32];
+        let rdata = [&[3, 1, 1][..], &digest].concat();
+        let data = message(
+            [0, 1, 0, 0],
+            &record("_443._tcp.example.com", TypeField::TLSA as u16, &rdata),
+        );
+        let packet = DNSPacket::from(&data).unwrap();
+        assert_eq!(packet.answers[0].name.string, "_443._tcp.example.com");
+        let tlsa = packet.answers[0].tlsa.as_ref().unwrap();
+        assert_eq!(
+            *tlsa,
+            TlsaRecord {
+                usage: 3,
+                selector: 1,
+                matching_type: 1,
+                cert_association: digest.to_vec(),
+            }
+        );
+        assert_eq!(tlsa.to_bytes(), rdata);
+    }
+
+    #[test]
+    fn rejects_short_rdata() {
+        let rdata = [3, 1];
+        let mut reader = Cursor::new(&rdata[..]);
+        assert!(TlsaRecord::from_reader(&mut reader, rdata.len() as u16).is_err());
+    }
+}
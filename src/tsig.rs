@@ -0,0 +1,111 @@
+use std::io::Cursor;
+
+use crate::reader::{read_bytes, read_u16, read_u32};
+use crate::DomainName;
+
+/// A transaction signature, it authenticates a whole message with a shared secret
+/// and is always the last record of the additional section.
+/// See https://datatracker.ietf.org/doc/html/rfc8945#section-4.2
+#[derive(Debug, Clone, PartialEq)]
+pub struct TsigRecord {
+    /// the MAC algorithm, e.g. `hmac-sha256`
+    pub algorithm_name: DomainName,
+    /// when the message was signed, in seconds since the epoch (48 bits)
+    pub time_signed: u64,
+    /// seconds of error permitted in `time_signed`
+    pub fudge: u16,
+    pub mac: Vec<u8>,
+    /// the ID of the message before it was forwarded
+    pub original_id: u16,
+    /// the extended RCODE of TSIG processing
+    pub error: u16,
+    /// the server time when `error` is BADTIME, empty otherwise
+    pub other_data: Vec<u8>,
+}
+impl TsigRecord {
+    pub fn from_reader(reader: &mut Cursor<&[u8]>) -> Result<Self, std::io::Error> {
+        let algorithm_name = DomainName::from_reader(reader)?;
+        let time_high = read_u16(reader)?;
+        let time_low = read_u32(reader)?;
+        let fudge = read_u16(reader)?;
+        let mac_size = read_u16(reader)?;
+        let mac = read_bytes(reader, mac_size as usize)?;
+        let original_id = read_u16(reader)?;
+        let error = read_u16(reader)?;
+        let other_len = read_u16(reader)?;
+        let other_data = read_bytes(reader, other_len as usize)?;
+
+        Ok(TsigRecord {
+            algorithm_name,
+            time_signed: (u64::from(time_high) << 32) | u64::from(time_low),
+            fudge,
+            mac,
+            original_id,
+            error,
+            other_data,
+        })
+    }
+
+    /// Only the lower 48 bits of `time_signed` are encoded.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.algorithm_name.to_bytes();
+        bytes.extend_from_slice(&self.time_signed.to_be_bytes()[2..]);
+        bytes.extend_from_slice(&self.fudge.to_be_bytes());
+        bytes.extend_from_slice(&(self.mac.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(&self.mac);
+        bytes.extend_from_slice(&self.original_id.to_be_bytes());
+        bytes.extend_from_slice(&self.error.to_be_bytes());
+        bytes.extend_from_slice(&(self.other_data.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(&self.other_data);
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{message, record};
+    use crate::{DNSPacket, TypeField};
+
+    fn sample_rdata() -> Vec<u8> {
+        let mut rdata = DomainName::from("hmac-sha256").to_bytes();
+        rdata.extend_from_slice(&[0x00, 0x01, 0x65, 0x92, 0x00, 0x00]);
+        rdata.extend_from_slice(&300u16.to_be_bytes());
+        rdata.extend_from_slice(&4u16.to_be_bytes());
+        rdata.extend_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+        rdata.extend_from_slice(&0x1234u16.to_be_bytes());
+        rdata.extend_from_slice(&0u16.to_be_bytes());
+        rdata.extend_from_slice(&0u16.to_be_bytes());
+        rdata
+    }
+
+    #[test]
+    fn decodes_tsig_rdata() {
+        let rdata = sample_rdata();
+        let tsig = TsigRecord::from_reader(&mut Cursor::new(&rdata[..])).unwrap();
+        assert_eq!(
+            tsig,
+            TsigRecord {
+                algorithm_name: DomainName::from("hmac-sha256"),
+                time_signed: 0x0001_6592_0000,
+                fudge: 300,
+                mac: vec![0xde, 0xad, 0xbe, 0xef],
+                original_id: 0x1234,
+                error: 0,
+                other_data: vec![],
+            }
+        );
+        assert_eq!(tsig.to_bytes(), rdata);
+    }
+
+    #[test]
+    fn decodes_tsig_record() {
+        let data = message(
+            [0, 0, 0, 1],
+            &record("key.example.com", TypeField::TSIG as u16, &sample_rdata()),
+        );
+        let packet = DNSPacket::from(&data).unwrap();
+        let tsig = packet.additionals[0].tsig.as_ref().unwrap();
+        assert_eq!(tsig.original_id, 0x1234);
+    }
+}
@@ -0,0 +1,77 @@
+use std::io::{Cursor, Error, ErrorKind};
+
+use crate::reader::{read_bytes, read_u16};
+
+/// A URI the owner name maps to, e.g. `_http._tcp.example.com` to
+/// `https://www.example.com/`.
+/// See https://datatracker.ietf.org/doc/html/rfc7553#section-4.5
+#[derive(Debug, Clone, PartialEq)]
+pub struct UriRecord {
+    /// lower values are tried first
+    pub priority: u16,
+    /// the relative weight of targets with the same priority
+    pub weight: u16,
+    /// the URI, taking the rest of the rdata without a length prefix
+    pub target: String,
+}
+impl UriRecord {
+    /// Reads the rdata starting at the current position of the reader, `data_len`
+    /// bytes long.
+    pub fn from_reader(reader: &mut Cursor<&[u8]>, data_len: u16) -> Result<Self, std::io::Error> {
+        let target_len = data_len
+            .checked_sub(4)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "URI rdata too short"))?;
+        let priority = read_u16(reader)?;
+        let weight = read_u16(reader)?;
+        let target = String::from_utf8(read_bytes(reader, target_len as usize)?)
+            .map_err(|_| Error::new(ErrorKind::InvalidData, "URI target isn't UTF-8"))?;
+        Ok(UriRecord {
+            priority,
+            weight,
+            target,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.priority.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&self.weight.to_be_bytes());
+        bytes.extend_from_slice(self.target.as_bytes());
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{message, record};
+    use crate::{DNSPacket, TypeField};
+
+    #[test]
+    fn decodes_https_target() {
+        let mut rdata = vec![0, 10, 0, 1];
+        rdata.extend_from_slice(b"https://www.example.com/");
+        let data = message(
+            [0, 1, 0, 0],
+            &record("_http._tcp.example.com", TypeField::URI as u16, &rdata),
+        );
+        let packet = DNSPacket::from(&data).unwrap();
+        assert_eq!(
+            packet.answers[0].uri,
+            Some(UriRecord {
+                priority: 10,
+                weight: 1,
+                target: "https://www.example.com/".to_string(),
+            })
+        );
+        assert_eq!(packet.answers[0].uri.as_ref().unwrap().to_bytes(), rdata);
+    }
+
+    #[test]
+    fn rejects_short_rdata() {
+        let data = message(
+            [0, 1, 0, 0],
+            &record("_http._tcp.example.com", TypeField::URI as u16, &[0, 10]),
+        );
+        assert!(DNSPacket::from(&data).is_err());
+    }
+}